@@ -0,0 +1,318 @@
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use crate::card::{TileType, Resource};
+use crate::effect::{Placement, TilePlacement};
+use crate::game_state::GameState;
+use crate::commands::CannotExecute;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Tile {
+    pub x: u32,
+    pub z: u32,
+    pub tile_type: TileType,
+    pub name: String,
+    pub resources: Vec<Resources>,
+    pub reserved: TileType,
+    pub owner: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SpecialTile {
+    pub tile_type: TileType,
+    pub name: String,
+    pub resources: Vec<Resources>,
+    pub reserved: TileType,
+    pub owner: Option<usize>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Resources {
+    Steel,
+    Titanium,
+    Card,
+    Plant,
+}
+
+// The tiles use axial coordinates: x is the row, z the position within the row.
+// Each tile has six neighbours, two in its own row and two in each adjacent row.
+pub fn is_adjacent(a: &Tile, b: &Tile) -> bool {
+    let dx = b.x as i32 - a.x as i32;
+    let dz = b.z as i32 - a.z as i32;
+    match (dx, dz) {
+        (0, 1) | (0, -1) | (1, 0) | (-1, 0) | (1, -1) | (-1, 1) => true,
+        _ => false,
+    }
+}
+
+pub fn get_tile<'a>(tiles: &'a Vec<Tile>, x: u32, z: u32) -> Option<&'a Tile> {
+    tiles.iter().find(|t| t.x == x && t.z == z)
+}
+
+pub fn neighbours<'a>(tiles: &'a Vec<Tile>, tile: &Tile) -> Vec<&'a Tile> {
+    tiles.iter().filter(|t| is_adjacent(tile, t)).collect()
+}
+
+fn is_city(tile_type: &TileType) -> bool {
+    *tile_type == TileType::City || *tile_type == TileType::Capital
+}
+
+// spaces a greenery has to be placed on if the player already owns tiles with free neighbours
+fn greenery_spaces<'a>(tiles: &'a Vec<Tile>, player_id: usize) -> Vec<&'a Tile> {
+    tiles.iter()
+        .filter(|t| t.tile_type == TileType::Empty && t.reserved == TileType::Empty)
+        .filter(|t| neighbours(tiles, t).iter().any(|n| n.owner == Some(player_id)))
+        .collect()
+}
+
+pub fn check_placement(game_state: &GameState, player_id: usize, placement: &TilePlacement, x: u32, z: u32) -> undo::Result {
+    let tiles = &game_state.tiles;
+    let tile = match get_tile(tiles, x, z) {
+        Some(tile) => tile,
+        None => return CannotExecute::new(format!("There is no space at ({}, {}) on the board!", x, z)),
+    };
+    if tile.tile_type != TileType::Empty {
+        return CannotExecute::new(format!("Space ({}, {}) is already occupied by {:?}!", x, z, tile.tile_type));
+    }
+    let tile_type = &placement.tile_type;
+    match &placement.placement {
+        Placement::Normal => {
+            let reserved = match tile_type {
+                TileType::Ocean => TileType::Ocean,
+                _ => TileType::Empty,
+            };
+            if tile.reserved != reserved {
+                return CannotExecute::new(format!("Cannot place {:?} on space ({}, {}) reserved for {:?}!", tile_type, x, z, tile.reserved));
+            }
+            if is_city(tile_type) && neighbours(tiles, tile).iter().any(|n| is_city(&n.tile_type)) {
+                return CannotExecute::new(format!("Cannot place a city at ({}, {}) next to another city!", x, z));
+            }
+            if *tile_type == TileType::Greenery {
+                let spaces = greenery_spaces(tiles, player_id);
+                if !spaces.is_empty() && !spaces.iter().any(|t| t.x == x && t.z == z) {
+                    return CannotExecute::new(format!("Greenery of player {} has to be placed next to one of their tiles!", player_id));
+                }
+            }
+        }
+        Placement::OceanArea => {
+            if tile.reserved != TileType::Ocean {
+                return CannotExecute::new(format!("{:?} has to be placed on an area reserved for oceans!", tile_type));
+            }
+        }
+        Placement::NonOceanArea => {
+            if tile.reserved == TileType::Ocean {
+                return CannotExecute::new(format!("{:?} has to be placed on an area not reserved for oceans!", tile_type));
+            }
+        }
+        Placement::Reserved => {
+            if tile.reserved != *tile_type {
+                return CannotExecute::new(format!("{:?} has to be placed on its reserved area!", tile_type));
+            }
+        }
+        Placement::Isolated => {
+            if tile.reserved != TileType::Empty || neighbours(tiles, tile).iter().any(|n| n.tile_type != TileType::Empty) {
+                return CannotExecute::new(format!("{:?} has to be placed next to no other tile!", tile_type));
+            }
+        }
+        Placement::OffMars(name) => {
+            return CannotExecute::new(format!("{} is not placed on the board!", name));
+        }
+    }
+    Ok(())
+}
+
+// In a solo game two neutral cities, each with an adjacent greenery, are placed at random.
+// The tiles belong to no player and do not raise the oxygen.
+pub fn place_neutral_cities(tiles: &mut Vec<Tile>) -> () {
+    let mut rng = rand::thread_rng();
+    for _ in 0..2 {
+        let city = tiles.iter()
+            .filter(|t| t.tile_type == TileType::Empty && t.reserved == TileType::Empty)
+            .filter(|t| !neighbours(tiles, t).iter().any(|n| is_city(&n.tile_type)))
+            .map(|t| (t.x, t.z))
+            .collect::<Vec<(u32, u32)>>()
+            .choose(&mut rng)
+            .copied();
+        let (x, z) = match city {
+            Some(coords) => coords,
+            None => return,
+        };
+        let greenery = neighbours(tiles, get_tile(tiles, x, z).unwrap()).iter()
+            .filter(|t| t.tile_type == TileType::Empty && t.reserved == TileType::Empty)
+            .map(|t| (t.x, t.z))
+            .collect::<Vec<(u32, u32)>>()
+            .choose(&mut rng)
+            .copied();
+        for (coords, tile_type) in vec![(Some((x, z)), TileType::City), (greenery, TileType::Greenery)] {
+            if let Some(tile) = coords.and_then(|(x, z)| tiles.iter_mut().find(|t| t.x == x && t.z == z)) {
+                tile.tile_type = tile_type;
+            }
+        }
+    }
+}
+
+// resources printed on the space plus 2 Megacredits for each adjacent ocean
+pub fn placement_bonus(tiles: &Vec<Tile>, x: u32, z: u32) -> (Vec<Resource>, usize) {
+    let tile = match get_tile(tiles, x, z) {
+        Some(tile) => tile,
+        None => return (vec![], 0),
+    };
+    let mut rescs = Vec::new();
+    let mut cards = 0;
+    for bonus in tile.resources.iter() {
+        match bonus {
+            Resources::Steel => rescs.push(Resource::Steel(1)),
+            Resources::Titanium => rescs.push(Resource::Titanium(1)),
+            Resources::Plant => rescs.push(Resource::Plants(1)),
+            Resources::Card => cards += 1,
+        }
+    }
+    let oceans = neighbours(tiles, tile).iter().filter(|n| n.tile_type == TileType::Ocean).count();
+    if oceans > 0 {
+        rescs.push(Resource::MegaCredits(2 * oceans as i32));
+    }
+    (rescs, cards)
+}
+
+pub fn tharsis() -> Vec<Tile> {
+    vec![
+        Tile {x: 0, z: 4, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Steel, Resources::Steel], reserved: TileType::Empty, owner: None},
+        Tile {x: 0, z: 5, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Steel, Resources::Steel], reserved: TileType::Ocean, owner: None},
+        Tile {x: 0, z: 6, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty, owner: None},
+        Tile {x: 0, z: 7, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Card], reserved: TileType::Ocean, owner: None},
+        Tile {x: 0, z: 8, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Ocean, owner: None},
+        Tile {x: 1, z: 3, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty, owner: None},
+        Tile {x: 1, z: 4, tile_type: TileType::Empty, name: "Tharsis Tholus".to_owned(), resources: vec![Resources::Steel], reserved: TileType::Empty, owner: None},
+        Tile {x: 1, z: 5, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty, owner: None},
+        Tile {x: 1, z: 6, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty, owner: None},
+        Tile {x: 1, z: 7, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty, owner: None},
+        Tile {x: 1, z: 8, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Card, Resources::Card], reserved: TileType::Ocean, owner: None},
+        Tile {x: 2, z: 2, tile_type: TileType::Empty, name: "Ascraeus Mons".to_owned(), resources: vec![Resources::Card], reserved: TileType::Empty, owner: None},
+        Tile {x: 2, z: 3, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty, owner: None},
+        Tile {x: 2, z: 4, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty, owner: None},
+        Tile {x: 2, z: 5, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty, owner: None},
+        Tile {x: 2, z: 6, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty, owner: None},
+        Tile {x: 2, z: 7, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty, owner: None},
+        Tile {x: 2, z: 8, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Steel], reserved: TileType::Empty, owner: None},
+        Tile {x: 3, z: 1, tile_type: TileType::Empty, name: "Pavonis Mons".to_owned(), resources: vec![Resources::Plant, Resources::Titanium], reserved: TileType::Empty, owner: None},
+        Tile {x: 3, z: 2, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant], reserved: TileType::Empty, owner: None},
+        Tile {x: 3, z: 3, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant], reserved: TileType::Empty, owner: None},
+        Tile {x: 3, z: 4, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant], reserved: TileType::Empty, owner: None},
+        Tile {x: 3, z: 5, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant, Resources::Plant], reserved: TileType::Empty, owner: None},
+        Tile {x: 3, z: 6, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant], reserved: TileType::Empty, owner: None},
+        Tile {x: 3, z: 7, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant], reserved: TileType::Empty, owner: None},
+        Tile {x: 3, z: 8, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant, Resources::Plant], reserved: TileType::Ocean, owner: None},
+        Tile {x: 4, z: 0, tile_type: TileType::Empty, name: "Arsia Mons".to_owned(), resources: vec![Resources::Plant, Resources::Plant], reserved: TileType::Empty, owner: None},
+        Tile {x: 4, z: 1, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant, Resources::Plant], reserved: TileType::Empty, owner: None},
+        Tile {x: 4, z: 2, tile_type: TileType::Empty, name: "Noctis City".to_owned(), resources: vec![Resources::Plant, Resources::Plant], reserved: TileType::City, owner: None},
+        Tile {x: 4, z: 3, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant, Resources::Plant], reserved: TileType::Ocean, owner: None},
+        Tile {x: 4, z: 4, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant, Resources::Plant], reserved: TileType::Ocean, owner: None},
+        Tile {x: 4, z: 5, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant, Resources::Plant], reserved: TileType::Ocean, owner: None},
+        Tile {x: 4, z: 6, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant, Resources::Plant], reserved: TileType::Empty, owner: None},
+        Tile {x: 4, z: 7, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant, Resources::Plant], reserved: TileType::Empty, owner: None},
+        Tile {x: 4, z: 8, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant, Resources::Plant], reserved: TileType::Empty, owner: None},
+        Tile {x: 5, z: 0, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant], reserved: TileType::Empty, owner: None},
+        Tile {x: 5, z: 1, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant, Resources::Plant], reserved: TileType::Empty, owner: None},
+        Tile {x: 5, z: 2, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant], reserved: TileType::Empty, owner: None},
+        Tile {x: 5, z: 3, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant], reserved: TileType::Empty, owner: None},
+        Tile {x: 5, z: 4, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant], reserved: TileType::Empty, owner: None},
+        Tile {x: 5, z: 5, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant], reserved: TileType::Ocean, owner: None},
+        Tile {x: 5, z: 6, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant], reserved: TileType::Ocean, owner: None},
+        Tile {x: 5, z: 7, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant], reserved: TileType::Ocean, owner: None},
+        Tile {x: 6, z: 0, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty, owner: None},
+        Tile {x: 6, z: 1, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty, owner: None},
+        Tile {x: 6, z: 2, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty, owner: None},
+        Tile {x: 6, z: 3, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty, owner: None},
+        Tile {x: 6, z: 4, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty, owner: None},
+        Tile {x: 6, z: 5, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Steel], reserved: TileType::Empty, owner: None},
+        Tile {x: 6, z: 6, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty, owner: None},
+        Tile {x: 7, z: 0, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Steel, Resources::Steel], reserved: TileType::Empty, owner: None},
+        Tile {x: 7, z: 1, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty, owner: None},
+        Tile {x: 7, z: 2, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Card], reserved: TileType::Empty, owner: None},
+        Tile {x: 7, z: 3, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Card], reserved: TileType::Empty, owner: None},
+        Tile {x: 7, z: 4, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty, owner: None},
+        Tile {x: 7, z: 5, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Titanium], reserved: TileType::Empty, owner: None},
+        Tile {x: 8, z: 0, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Steel], reserved: TileType::Empty, owner: None},
+        Tile {x: 8, z: 1, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Steel, Resources::Steel], reserved: TileType::Empty, owner: None},
+        Tile {x: 8, z: 2, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty, owner: None},
+        Tile {x: 8, z: 3, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty, owner: None},
+        Tile {x: 8, z: 4, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Titanium, Resources::Titanium], reserved: TileType::Ocean, owner: None}
+    ]
+}
+
+pub fn tharsis_special() -> Vec<SpecialTile> {
+    vec![
+        SpecialTile {name: "Phobos Space Haven".to_owned(), tile_type: TileType::Empty, resources: vec![], reserved: TileType::City, owner: None},
+        SpecialTile {name: "Ganymede Colony".to_owned(), tile_type: TileType::Empty, resources: vec![], reserved: TileType::City, owner: None}
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::basic_game;
+
+    fn occupy(game_state: &mut GameState, x: u32, z: u32, tile_type: TileType, owner: Option<usize>) {
+        let tile = game_state.tiles.iter_mut().find(|t| t.x == x && t.z == z).unwrap();
+        tile.tile_type = tile_type;
+        tile.owner = owner;
+    }
+
+    fn normal(tile_type: TileType) -> TilePlacement {
+        TilePlacement{tile_type, placement: Placement::Normal}
+    }
+
+    #[test]
+    fn tiles_have_six_neighbours_in_three_rows() {
+        let tiles = tharsis();
+        let tile = get_tile(&tiles, 4, 4).unwrap();
+        let mut coords: Vec<(u32, u32)> = neighbours(&tiles, tile).iter().map(|t| (t.x, t.z)).collect();
+        coords.sort();
+        assert_eq!(coords, vec![(3, 4), (3, 5), (4, 3), (4, 5), (5, 3), (5, 4)]);
+    }
+
+    #[test]
+    fn cities_are_not_placed_next_to_cities() {
+        let (mut game_state, _) = basic_game(2);
+        occupy(&mut game_state, 0, 4, TileType::City, Some(1));
+        assert!(check_placement(&game_state, 0, &normal(TileType::City), 1, 4).is_err());
+        assert!(check_placement(&game_state, 0, &normal(TileType::City), 2, 4).is_ok());
+    }
+
+    #[test]
+    fn oceans_are_placed_on_reserved_areas_only() {
+        let (game_state, _) = basic_game(2);
+        assert!(check_placement(&game_state, 0, &normal(TileType::Ocean), 0, 4).is_err());
+        assert!(check_placement(&game_state, 0, &normal(TileType::Ocean), 0, 5).is_ok());
+        assert!(check_placement(&game_state, 0, &normal(TileType::City), 0, 5).is_err());
+    }
+
+    #[test]
+    fn greeneries_are_placed_next_to_own_tiles() {
+        let (mut game_state, _) = basic_game(2);
+        assert!(check_placement(&game_state, 0, &normal(TileType::Greenery), 6, 0).is_ok());
+        occupy(&mut game_state, 2, 4, TileType::City, Some(0));
+        assert!(check_placement(&game_state, 0, &normal(TileType::Greenery), 6, 0).is_err());
+        assert!(check_placement(&game_state, 0, &normal(TileType::Greenery), 2, 5).is_ok());
+        // the tiles of other players do not count
+        assert!(check_placement(&game_state, 1, &normal(TileType::Greenery), 6, 0).is_ok());
+    }
+
+    #[test]
+    fn occupied_spaces_are_rejected() {
+        let (mut game_state, _) = basic_game(2);
+        occupy(&mut game_state, 6, 0, TileType::Greenery, Some(1));
+        assert!(check_placement(&game_state, 0, &normal(TileType::City), 6, 0).is_err());
+        assert!(check_placement(&game_state, 0, &normal(TileType::City), 9, 9).is_err());
+    }
+
+    #[test]
+    fn the_placement_bonus_includes_adjacent_oceans() {
+        let mut tiles = tharsis();
+        tiles.iter_mut().find(|t| t.x == 0 && t.z == 5).unwrap().tile_type = TileType::Ocean;
+        let (rescs, cards) = placement_bonus(&tiles, 0, 4);
+        assert_eq!(rescs, vec![Resource::Steel(1), Resource::Steel(1), Resource::MegaCredits(2)]);
+        assert_eq!(cards, 0);
+        assert_eq!(placement_bonus(&tiles, 1, 8), (vec![], 2));
+    }
+}
@@ -2,6 +2,9 @@ use serde::{Deserialize, Serialize};
 use std::convert::From;
 use std::fmt;
 
+use crate::effect::{Effect, Modifier, VictoryPoints};
+use crate::event::Trigger;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Card {
     pub name: String,
@@ -17,6 +20,16 @@ pub struct Card {
     pub terraforming_effect: TerraformingEffect,
     pub interactions: Interactions,
     pub text: Text,
+    pub effects: Vec<Effect>,
+    // the action of a blue card, empty if the card has none
+    pub action: Vec<Effect>,
+    // the first action of a corporation, empty if the corporation has none
+    pub first_action: Vec<Effect>,
+    // ongoing effects that react to events
+    pub triggers: Vec<Trigger>,
+    // discounts and relaxed requirements for the owner's cards
+    pub modifiers: Vec<Modifier>,
+    pub victory_points: VictoryPoints,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -33,6 +46,8 @@ pub struct GlobalRequirements {
     pub max_oxygen: u32,
     pub min_ocean: u32,
     pub max_ocean: u32,
+    pub min_venus: u32,
+    pub max_venus: u32,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -47,6 +62,7 @@ pub enum Tags {
     Earth,
     Jovian,
     Energy,
+    Venus,
     Event,
     Special
 }
@@ -89,6 +105,7 @@ pub struct TerraformingEffect {
     pub temperature: NumberOrRef,
     pub oxygen: NumberOrRef,
     pub ocean: NumberOrRef,
+    pub venus: NumberOrRef,
     pub tr: NumberOrRef,
     pub vp: NumberOrRef,
 }
@@ -160,6 +177,18 @@ pub enum Resource {
     MegaCredits(i32), Steel(i32), Titanium(i32), Plants(i32), Energy(i32), Heat(i32), Special,
 }
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TileType {
+    Empty,
+    City,
+    Greenery,
+    Ocean,
+    // a city tile with additional victory points for adjacent oceans
+    Capital,
+    // named tiles of project cards, e.g. Nuclear Zone or Mining Area
+    Special,
+}
+
 impl fmt::Display for Resource {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{:?}", self)
@@ -186,13 +215,50 @@ impl Resource {
             }
         }
     }
+
+    pub fn count(&self) -> i32 {
+        match *self {
+            Resource::MegaCredits(count) | Resource::Steel(count) | Resource::Titanium(count) |
+            Resource::Plants(count) | Resource::Energy(count) | Resource::Heat(count) => count,
+            Resource::Special => 0,
+        }
+    }
+
+    pub fn with_count(&self, count: i32) -> Resource {
+        match *self {
+            Resource::MegaCredits(_) => Resource::MegaCredits(count),
+            Resource::Steel(_) => Resource::Steel(count),
+            Resource::Titanium(_) => Resource::Titanium(count),
+            Resource::Plants(_) => Resource::Plants(count),
+            Resource::Energy(_) => Resource::Energy(count),
+            Resource::Heat(_) => Resource::Heat(count),
+            Resource::Special => Resource::Special,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count() == 0
+    }
+
+    pub fn times(&self, factor: i32) -> Resource {
+        match *self {
+            Resource::MegaCredits(count) => Resource::MegaCredits(count * factor),
+            Resource::Steel(count) => Resource::Steel(count * factor),
+            Resource::Titanium(count) => Resource::Titanium(count * factor),
+            Resource::Plants(count) => Resource::Plants(count * factor),
+            Resource::Energy(count) => Resource::Energy(count * factor),
+            Resource::Heat(count) => Resource::Heat(count * factor),
+            Resource::Special => Resource::Special,
+        }
+    }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum HoldableResource {
     Animals,
     Science,
     Microbes,
+    Floaters,
     TODO,
     None,
 }
@@ -204,7 +270,7 @@ impl From<String> for HoldableResource {
             "Science" => HoldableResource::Science,
             "Microbes" => HoldableResource::Microbes,
             "Ref" => HoldableResource::TODO,
-            "Floaters" => HoldableResource::TODO,
+            "Floaters" => HoldableResource::Floaters,
             "No" => HoldableResource::None,
             _ => HoldableResource::None // FIXME decide on behaviour here
         }
@@ -245,7 +311,7 @@ impl From<String> for BoolOrRef {
 
 impl From<CSVCard> for Card {
     fn from(csv_card: CSVCard) -> Self {
-        Card {
+        let mut card = Card {
             name: csv_card.card_name.to_owned(),
             id: match csv_card.id.len() {
                 0 => csv_card.card_name,
@@ -265,6 +331,8 @@ impl From<CSVCard> for Card {
                     max_oxygen: csv_card.req_global_max_oxygen,
                     min_ocean: csv_card.req_global_ocean,
                     max_ocean: csv_card.req_global_max_ocean,
+                    min_venus: csv_card.req_global_venus as u32,
+                    max_venus: csv_card.req_global_max_venus as u32,
                 },
                 // There are cards that require tiles in play, i.e. cities, colonies or greeneries
                 // TODO find a consistent way to deal with them
@@ -279,6 +347,7 @@ impl From<CSVCard> for Card {
                     vec![Tags::Earth; csv_card.req_local_earth as usize],
                     vec![Tags::Jovian; csv_card.req_local_jovian as usize],
                     vec![Tags::Energy; csv_card.req_local_energy as usize],
+                    vec![Tags::Venus; csv_card.req_local_venus as usize],
                     vec![Tags::Special; match csv_card.req_local_other.as_ref() {"Ref" => 1, _ => 0}],
                 ].concat(),
             },
@@ -293,6 +362,7 @@ impl From<CSVCard> for Card {
                 Tags::from_string(&csv_card.tag_earth, Tags::Earth),
                 Tags::from_string(&csv_card.tag_jovian, Tags::Jovian),
                 Tags::from_string(&csv_card.tag_energy, Tags::Energy),
+                Tags::from_string(&csv_card.tag_venus, Tags::Venus),
                 Tags::from_string(&csv_card.tag_event, Tags::Event),
             ].concat(),
             production: vec![
@@ -316,6 +386,7 @@ impl From<CSVCard> for Card {
                 temperature: NumberOrRef::from(csv_card.temperature),
                 oxygen: NumberOrRef::from(csv_card.oxygen),
                 ocean: NumberOrRef::from(csv_card.ocean),
+                venus: NumberOrRef::from(csv_card.venus),
                 tr: NumberOrRef::from(csv_card.tr),
                 vp: NumberOrRef::from(csv_card.vp),
             },
@@ -329,8 +400,24 @@ impl From<CSVCard> for Card {
             text: Text {
                 action_or_ongoing_effect_text: csv_card.action_or_on_going_effect_text,
                 onetime_effect_text: csv_card.one_time_effect_text,
-            }
-        }
+            },
+            effects: vec![],
+            action: vec![],
+            first_action: vec![],
+            triggers: vec![],
+            modifiers: vec![],
+            victory_points: VictoryPoints::Fixed(0),
+        };
+        card.victory_points = VictoryPoints::from_card(&card.name, &card.terraforming_effect.vp);
+        card.effects = match Effect::special_effects(&card.name) {
+            Some(effects) => effects,
+            None => Effect::from_card(&card),
+        };
+        card.action = Effect::card_action(&card.name).unwrap_or_default();
+        card.first_action = Effect::first_action(&card.name).unwrap_or_default();
+        card.triggers = Trigger::card_triggers(&card.name).unwrap_or_default();
+        card.modifiers = Modifier::card_modifiers(&card.name).unwrap_or_default();
+        card
     }
 }
 
@@ -0,0 +1,198 @@
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use crate::card::{Resource, HoldableResource};
+use crate::game_state::GameState;
+
+// a colony tile has room for three colonies
+pub const MAX_COLONIES: usize = 3;
+// the last position of the colony track
+pub const MAX_TRACK: u32 = 6;
+// trading costs either megacredits, energy or titanium
+pub const TRADE_MEGACREDITS: u32 = 9;
+pub const TRADE_ENERGY: u32 = 3;
+pub const TRADE_TITANIUM: u32 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Colonies {
+    Callisto,
+    Ceres,
+    Enceladus,
+    Europa,
+    Ganymede,
+    Io,
+    Luna,
+    Miranda,
+    Pluto,
+    Titan,
+    Triton,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ColonyTile {
+    pub name: Colonies,
+    // position of the marker on the colony track
+    pub track: u32,
+    // owners of the colonies, in the order they were placed
+    pub colonies: Vec<usize>,
+    // the player whose trade fleet is docked here
+    pub trader: Option<usize>,
+}
+
+// what a colony tile hands out when a colony is placed, when trading and to the colonists
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ColonyBonus {
+    Resource(Resource),
+    Production(Resource),
+    Cards(u32),
+    // draws the cards, then the player discards as many cards from the hand
+    DrawDiscard(u32),
+    // added to a card of the player's choice
+    CardResource(HoldableResource, u32),
+    Ocean,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TradePayment {
+    MegaCredits,
+    Energy,
+    Titanium,
+}
+
+impl TradePayment {
+    // the cost of a trade before discounts
+    pub fn resource(&self) -> Resource {
+        match self {
+            TradePayment::MegaCredits => Resource::MegaCredits(TRADE_MEGACREDITS as i32),
+            TradePayment::Energy => Resource::Energy(TRADE_ENERGY as i32),
+            TradePayment::Titanium => Resource::Titanium(TRADE_TITANIUM as i32),
+        }
+    }
+}
+
+impl Colonies {
+    pub fn all() -> Vec<Colonies> {
+        use Colonies::*;
+        vec![Callisto, Ceres, Enceladus, Europa, Ganymede, Io, Luna, Miranda, Pluto, Titan, Triton]
+    }
+
+    // Miranda, Titan and Enceladus stay inactive until a card that holds their resource is played
+    pub fn holds(&self) -> Option<HoldableResource> {
+        match self {
+            Colonies::Miranda => Some(HoldableResource::Animals),
+            Colonies::Titan => Some(HoldableResource::Floaters),
+            Colonies::Enceladus => Some(HoldableResource::Microbes),
+            _ => None,
+        }
+    }
+
+    pub fn placement_bonus(&self) -> ColonyBonus {
+        match self {
+            Colonies::Callisto => ColonyBonus::Production(Resource::Energy(1)),
+            Colonies::Ceres => ColonyBonus::Production(Resource::Steel(1)),
+            Colonies::Enceladus => ColonyBonus::CardResource(HoldableResource::Microbes, 3),
+            Colonies::Europa => ColonyBonus::Ocean,
+            Colonies::Ganymede => ColonyBonus::Production(Resource::Plants(1)),
+            Colonies::Io => ColonyBonus::Production(Resource::Heat(1)),
+            Colonies::Luna => ColonyBonus::Production(Resource::MegaCredits(2)),
+            Colonies::Miranda => ColonyBonus::CardResource(HoldableResource::Animals, 1),
+            Colonies::Pluto => ColonyBonus::Cards(2),
+            Colonies::Titan => ColonyBonus::CardResource(HoldableResource::Floaters, 3),
+            Colonies::Triton => ColonyBonus::Resource(Resource::Titanium(3)),
+        }
+    }
+
+    // every colonist on the tile gets this bonus for each of their colonies when someone trades
+    pub fn colony_bonus(&self) -> ColonyBonus {
+        match self {
+            Colonies::Callisto => ColonyBonus::Resource(Resource::Energy(3)),
+            Colonies::Ceres => ColonyBonus::Resource(Resource::Steel(2)),
+            Colonies::Enceladus => ColonyBonus::CardResource(HoldableResource::Microbes, 1),
+            Colonies::Europa => ColonyBonus::Resource(Resource::MegaCredits(1)),
+            Colonies::Ganymede => ColonyBonus::Resource(Resource::Plants(1)),
+            Colonies::Io => ColonyBonus::Resource(Resource::Heat(2)),
+            Colonies::Luna => ColonyBonus::Resource(Resource::MegaCredits(2)),
+            Colonies::Miranda => ColonyBonus::Cards(1),
+            Colonies::Pluto => ColonyBonus::DrawDiscard(1),
+            Colonies::Titan => ColonyBonus::CardResource(HoldableResource::Floaters, 1),
+            Colonies::Triton => ColonyBonus::Resource(Resource::Titanium(1)),
+        }
+    }
+
+    // the trader's income for the marker position on the colony track
+    pub fn trade_income(&self, track: u32) -> ColonyBonus {
+        let step = track.min(MAX_TRACK) as usize;
+        let income = |table: [i32; 7]| table[step];
+        match self {
+            Colonies::Callisto => ColonyBonus::Resource(Resource::Energy(income([0, 2, 3, 5, 7, 10, 13]))),
+            Colonies::Ceres => ColonyBonus::Resource(Resource::Steel(income([1, 2, 3, 4, 6, 8, 10]))),
+            Colonies::Enceladus => ColonyBonus::CardResource(HoldableResource::Microbes, income([0, 1, 2, 3, 4, 4, 5]) as u32),
+            Colonies::Europa => ColonyBonus::Production(Resource::MegaCredits(income([1, 1, 2, 2, 3, 3, 4]))),
+            Colonies::Ganymede => ColonyBonus::Resource(Resource::Plants(income([0, 1, 2, 3, 4, 5, 6]))),
+            Colonies::Io => ColonyBonus::Resource(Resource::Heat(income([2, 3, 4, 6, 8, 10, 13]))),
+            Colonies::Luna => ColonyBonus::Resource(Resource::MegaCredits(income([1, 2, 4, 7, 10, 13, 17]))),
+            Colonies::Miranda => ColonyBonus::CardResource(HoldableResource::Animals, income([0, 1, 1, 2, 2, 3, 3]) as u32),
+            Colonies::Pluto => ColonyBonus::Cards(income([0, 1, 2, 2, 3, 3, 4]) as u32),
+            Colonies::Titan => ColonyBonus::CardResource(HoldableResource::Floaters, income([0, 1, 1, 2, 3, 3, 4]) as u32),
+            Colonies::Triton => ColonyBonus::Resource(Resource::Titanium(income([0, 1, 1, 2, 3, 4, 5]))),
+        }
+    }
+}
+
+impl ColonyTile {
+    // the marker starts on the second position of the track
+    pub fn new(name: Colonies) -> ColonyTile {
+        ColonyTile {
+            name: name,
+            track: 1,
+            colonies: Vec::new(),
+            trader: None,
+        }
+    }
+
+    pub fn is_active(&self, game_state: &GameState) -> bool {
+        match self.name.holds() {
+            Some(resource) => game_state.cards_in_play.iter().any(|c| c.card.interactions.holds_resources == resource),
+            None => true,
+        }
+    }
+
+    // the marker cannot be left of the next free colony space
+    pub fn min_track(&self) -> u32 {
+        self.colonies.len() as u32
+    }
+}
+
+// Two more colony tiles than players are used, but at least five. A solo game uses four.
+pub fn deal_colonies(player_count: usize) -> Vec<ColonyTile> {
+    let count = match player_count {
+        1 => 4,
+        n => (n + 2).max(5),
+    };
+    let mut rng = rand::thread_rng();
+    let mut colonies = Colonies::all();
+    colonies.shuffle(&mut rng);
+    colonies.into_iter().take(count).map(ColonyTile::new).collect()
+}
+
+pub fn get_colony<'a>(colonies: &'a Vec<ColonyTile>, name: &Colonies) -> Option<&'a ColonyTile> {
+    colonies.iter().find(|c| c.name == *name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn two_more_tiles_than_players_are_dealt() {
+        assert_eq!(deal_colonies(1).len(), 4);
+        assert_eq!(deal_colonies(2).len(), 5);
+        assert_eq!(deal_colonies(5).len(), 7);
+    }
+
+    #[test]
+    fn the_income_stops_at_the_end_of_the_track() {
+        assert_eq!(Colonies::Luna.trade_income(0), ColonyBonus::Resource(Resource::MegaCredits(1)));
+        assert_eq!(Colonies::Luna.trade_income(MAX_TRACK + 1), ColonyBonus::Resource(Resource::MegaCredits(17)));
+    }
+}
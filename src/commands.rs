@@ -2,8 +2,48 @@ use undo::Command;
 use serde::{Deserialize, Serialize};
 use std::{error::Error, fmt};
 
-use crate::game_state::{GameState, OwnedCard, Phase};
-use crate::card::{Card, CardType, Tags, Resource};
+use crate::game_state::{GameState, OwnedCard, Phase, Milestones, Awards, UNSUPPORTED_CORPORATIONS, MAX_TEMPERATURE, TEMPERATURE_STEP, MAX_OXYGEN, MAX_OCEANS, MAX_VENUS, VENUS_STEP};
+use crate::card::{Card, CardType, Tags, Resource, TileType, HoldableResource};
+use crate::effect::{Placement, TilePlacement, GlobalParameter};
+use crate::board;
+use crate::colony::{self, Colonies, TradePayment, MAX_COLONIES};
+use crate::player::{Player, ActionState, ACTIONS_PER_TURN, MIN_MEGACREDIT_PRODUCTION};
+
+// Joins commands like undo's Chain, but the commands applied before a failing one are undone again,
+// so a rejected action leaves no trace in the state. A failing command must not change the state itself.
+pub struct Transaction{commands: Vec<Box<dyn Command<GameState>>>}
+
+impl Transaction {
+    pub fn new() -> Transaction {
+        Transaction{commands: Vec::new()}
+    }
+
+    pub fn join(mut self, command: impl Command<GameState> + 'static) -> Transaction {
+        self.commands.push(Box::new(command));
+        self
+    }
+}
+
+impl Command<GameState> for Transaction {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        for idx in 0..self.commands.len() {
+            if let Err(err) = self.commands[idx].apply(game_state) {
+                for command in self.commands[..idx].iter_mut().rev() {
+                    command.undo(game_state)?;
+                }
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
+        for command in self.commands.iter_mut().rev() {
+            command.undo(game_state)?;
+        }
+        Ok(())
+    }
+}
 
 pub struct DrawCards{pub player_id: usize, pub count: usize, pub card_type: CardType}
 
@@ -17,6 +57,11 @@ impl Command<GameState> for DrawCards {
                 let player = game_state.get_player_mut(self.player_id)?;
                 player.draft_corporations(cards.as_mut());
             }
+            CardType::Prelude => {
+                let mut cards = game_state.prelude_pile.draw_cards(self.count);
+                let player = game_state.get_player_mut(self.player_id)?;
+                player.draft_preludes(cards.as_mut());
+            }
             _ => {
                 let mut cards = game_state.project_pile.draw_cards(self.count);
                 let player = game_state.get_player_mut(self.player_id)?;
@@ -34,6 +79,10 @@ impl Command<GameState> for DrawCards {
                 let mut cards = player.hand.drain(draw_range).rev().collect::<Vec<Card>>();
                 game_state.corporation_pile.draw_pile.append(cards.as_mut());
             }
+            CardType::Prelude => {
+                let mut cards = player.hand.drain(draw_range).rev().collect::<Vec<Card>>();
+                game_state.prelude_pile.draw_pile.append(cards.as_mut());
+            }
             _ => {
                 let mut cards = player.research_queue.drain(draw_range).rev().collect::<Vec<Card>>();
                 game_state.project_pile.draw_pile.append(cards.as_mut());
@@ -43,32 +92,120 @@ impl Command<GameState> for DrawCards {
     }
 }
 
+// draws projects straight into the player's hand, e.g. for "Draw 2 cards."
+pub struct DrawProjects{pub player_id: usize, pub count: usize}
+
+impl Command<GameState> for DrawProjects {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        let mut cards = game_state.project_pile.draw_cards(self.count);
+        game_state.get_player_mut(self.player_id)?.hand.append(cards.as_mut());
+        Ok(())
+    }
+
+    fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
+        let player = game_state.get_player_mut(self.player_id)?;
+        let draw_range = player.hand.len()-self.count..;
+        let mut cards = player.hand.drain(draw_range).rev().collect::<Vec<Card>>();
+        game_state.project_pile.draw_pile.append(cards.as_mut());
+        Ok(())
+    }
+}
+
+// check if player is active player and phase must be Action
+fn check_action(game_state: &GameState, player_id: usize, action: &str) -> undo::Result {
+    if game_state.phase != Phase::Action {
+        return CannotExecute::new(format!("Cannot {} in Phase {:?}", action, game_state.phase));
+    }
+    if player_id != game_state.active_player {
+        return CannotExecute::new(format!("Player {} cannot {}. Active player is {}", player_id, action, game_state.active_player));
+    }
+    Ok(())
+}
+
+// Without a payment the card is paid with megacredits only.
+// The target card receives or loses resources if the card's effects do not use its own.
+// The colony is the colony tile for cards that place a colony.
 #[derive(Debug, Deserialize, Serialize)]
-pub struct PlayCard{pub owner_id: usize, pub card_id: String, pub target_id: Option<usize>}
+pub struct PlayCard{
+    pub owner_id: usize,
+    pub card_id: String,
+    pub target_id: Option<usize>,
+    pub target_card: Option<String>,
+    pub choice: Option<usize>,
+    pub payment: Option<Payment>,
+    pub colony: Option<Colonies>,
+    #[serde(skip)] pub paid: Option<Payment>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct Payment{pub megacredits: u32, pub steel: u32, pub titanium: u32}
+
+fn check_payment(card: &Card, payment: &Payment, player_id: usize, game_state: &GameState) -> undo::Result {
+    check_payment_of(card, game_state.card_cost(player_id, card), payment, player_id, game_state)
+}
+
+// Steel and titanium may overpay a card if there is no exact way to pay it,
+// but no single unit must be superfluous. Card actions with a cost are paid like the card itself.
+pub fn check_payment_of(card: &Card, cost: u32, payment: &Payment, player_id: usize, game_state: &GameState) -> undo::Result {
+    let player = game_state.get_player(player_id)?;
+    if payment.steel > 0 && !card.tags.contains(&Tags::Building) {
+        return CannotExecute::new(format!("Cannot pay card {} with steel, it has no building tag!", card.id));
+    }
+    if payment.titanium > 0 && !card.tags.contains(&Tags::Space) {
+        return CannotExecute::new(format!("Cannot pay card {} with titanium, it has no space tag!", card.id));
+    }
+    if player.inventory.megacredits < payment.megacredits
+        || player.inventory.steel < payment.steel
+        || player.inventory.titanium < payment.titanium {
+            return CannotExecute::new(format!("Insufficient funds! Player {} cannot pay {:?}!", player_id, payment));
+    }
+    let steel_value = game_state.steel_value(player_id);
+    let titanium_value = game_state.titanium_value(player_id);
+    let total = payment.megacredits + payment.steel * steel_value + payment.titanium * titanium_value;
+    if total < cost {
+        return CannotExecute::new(format!("Insufficient funds! Player {} pays {} of {} Megacredits for card {}!", player_id, total, cost, card.id));
+    }
+    let surplus = total - cost;
+    if (payment.megacredits > 0 && surplus > 0)
+        || (payment.steel > 0 && surplus >= steel_value)
+        || (payment.titanium > 0 && surplus >= titanium_value) {
+            return CannotExecute::new(format!("Player {} overpays card {} by {} Megacredits!", player_id, card.id, surplus));
+    }
+    Ok(())
+}
 
 fn check_requirements(card: &Card, player_id: usize, game_state: &GameState) -> undo::Result {
-    if game_state.temperature < card.requirements.global.min_temperature ||
-        game_state.temperature > card.requirements.global.max_temperature {
+    // the window is relaxed by the requirement modifiers of the player's cards
+    let window = game_state.requirement_window(player_id, card);
+    if game_state.temperature < window.min_temperature ||
+        game_state.temperature > window.max_temperature {
             return CannotExecute::new(
                 format!("Requirements not met! Temperature {} <= {} <= {} not satisfied!",
-                        card.requirements.global.min_temperature, game_state.temperature, card.requirements.global.max_temperature)
+                        window.min_temperature, game_state.temperature, window.max_temperature)
             )
     }
-    if game_state.oxygen < card.requirements.global.min_oxygen ||
-        game_state.oxygen > card.requirements.global.max_oxygen {
+    if game_state.oxygen < window.min_oxygen ||
+        game_state.oxygen > window.max_oxygen {
             return CannotExecute::new(
                 format!("Requirements not met! Oxygen {} <= {} <= {} not satisfied!",
-                        card.requirements.global.min_oxygen, game_state.oxygen, card.requirements.global.max_oxygen)
+                        window.min_oxygen, game_state.oxygen, window.max_oxygen)
             )
     }
-    if game_state.oceans_placed < card.requirements.global.min_ocean ||
-        game_state.oceans_placed > card.requirements.global.min_ocean {
+    if game_state.oceans_placed < window.min_ocean ||
+        game_state.oceans_placed > window.max_ocean {
             return CannotExecute::new(
                 format!("Requirements not met! Oceans {} <= {} <= {} not satisfied!",
-                        card.requirements.global.min_ocean, game_state.oceans_placed, card.requirements.global.max_ocean)
+                        window.min_ocean, game_state.oceans_placed, window.max_ocean)
+            )
+    }
+    if game_state.venus < window.min_venus ||
+        game_state.venus > window.max_venus {
+            return CannotExecute::new(
+                format!("Requirements not met! Venus {} <= {} <= {} not satisfied!",
+                        window.min_venus, game_state.venus, window.max_venus)
             )
     }
-    let mut owned_tags: Vec<&Tags> = game_state.cards_in_play.iter().filter(|c| c.owner == player_id).map(|c| &c.card.tags).flatten().collect();
+    let mut owned_tags: Vec<&Tags> = game_state.player_tags(player_id);
     // check requirements by removing ("counting") tags from owned tags if they are required by the card
     for tag in card.requirements.local.iter() {
         match owned_tags.iter().position(|t| **t == *tag) {
@@ -83,28 +220,25 @@ fn check_requirements(card: &Card, player_id: usize, game_state: &GameState) ->
 
 impl Command<GameState> for PlayCard {
     fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
-        // check if player is active player and phase must be Action
-        if game_state.phase != Phase::Action {
-            return CannotExecute::new(format!("Cannot PlayCard in Phase {:?}", game_state.phase));
-        }
-        if self.owner_id != game_state.active_player {
-            return CannotExecute::new(format!("Player {} cannot PlayCard. Active player is {}", self.owner_id, game_state.active_player));
-        }
-        let player = game_state.get_player_mut(self.owner_id)?;
+        check_action(game_state, self.owner_id, "PlayCard")?;
+        let player = game_state.get_player(self.owner_id)?;
         // check if player actually owns the card
-        let card = match player.hand.iter().position(|c| c.id == self.card_id) {
-            Some(idx) => player.hand.remove(idx),
+        let card = match player.hand.iter().find(|c| c.id == self.card_id) {
+            Some(card) => card.clone(),
             None => return CannotExecute::new(format!("Card {} not found in player {}'s hand!", self.card_id, self.owner_id)),
         };
-        // TODO allow to substitute megecredits with steel and titanium
-        if player.inventory.megacredits < card.cost {
-            return CannotExecute::new(format!("Insufficient funds! Player {} need {} Megacredits to play card {}!", self.owner_id, card.cost, card.id));
-        } else {
-            player.inventory.megacredits -= card.cost;
-        }
+        let cost = game_state.card_cost(self.owner_id, &card);
+        let payment = self.payment.clone().unwrap_or(Payment{megacredits: cost, steel: 0, titanium: 0});
+        check_payment(&card, &payment, self.owner_id, game_state)?;
         check_requirements(&card, self.owner_id, game_state)?;
-        // TODO check if actions on card can be executed (i.e. remove resources from other player)
-        game_state.cards_in_play.push(OwnedCard{card: card, owner: self.owner_id});
+        // the card's effects were checked by the StateMachine, failing ones undo the whole transaction
+        let player = game_state.get_player_mut(self.owner_id)?;
+        player.hand.retain(|c| c.id != self.card_id);
+        player.inventory.megacredits -= payment.megacredits;
+        player.inventory.steel -= payment.steel;
+        player.inventory.titanium -= payment.titanium;
+        self.paid = Some(payment);
+        game_state.cards_in_play.push(OwnedCard{card: card, owner: self.owner_id, resources: 0, used: false});
         Ok(())
     }
 
@@ -114,14 +248,23 @@ impl Command<GameState> for PlayCard {
             None => return CannotExecute::new(format!("Card {} not found in cards_in_player!", self.card_id)),
         };
         let player = game_state.get_player_mut(self.owner_id)?;
-        player.inventory.megacredits += owned_card.card.cost;
+        // refund exactly what was spent
+        let paid = self.paid.take().unwrap_or_default();
+        player.inventory.megacredits += paid.megacredits;
+        player.inventory.steel += paid.steel;
+        player.inventory.titanium += paid.titanium;
         player.hand.push(owned_card.card);
         Ok(())
     }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
-pub struct ChooseCorporation{pub player_id: usize, pub card_id: String}
+pub struct ChooseCorporation {
+    pub player_id: usize,
+    pub card_id: String,
+    // the number of corporations that were discarded
+    #[serde(skip)] pub rejected: usize,
+}
 
 impl Command<GameState> for ChooseCorporation {
     fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
@@ -129,25 +272,104 @@ impl Command<GameState> for ChooseCorporation {
             return CannotExecute::new("Can only select corporation in setup phase!".to_owned());
         }
         let player = game_state.get_player_mut(self.player_id)?;
-        let (mut chosen, mut rejected): (Vec<Card>, Vec<Card>) = player.hand.drain(..).partition(|c| c.id == self.card_id);
-        match chosen.pop() {
-            Some(card) => player.corporation = Some(card),
-            None => return CannotExecute::new(format!("Corporation {} not found in player {}'s hand!", self.card_id, self.player_id)),
+        let card_id = &self.card_id;
+        if !player.hand.iter().any(|c| c.card_type == CardType::Corporation && c.id == *card_id) {
+            return CannotExecute::new(format!("Corporation {} not found in player {}'s hand!", card_id, self.player_id));
+        }
+        if player.hand.iter().any(|c| c.id == *card_id && UNSUPPORTED_CORPORATIONS.contains(&c.name.as_str())) {
+            return CannotExecute::new(format!("Corporation {} is not supported yet!", card_id));
         }
+        // preludes and projects stay in the hand
+        let (corporations, others): (Vec<Card>, Vec<Card>) = player.hand.drain(..).partition(|c| c.card_type == CardType::Corporation);
+        player.hand = others;
+        let (mut chosen, mut rejected): (Vec<Card>, Vec<Card>) = corporations.into_iter().partition(|c| c.id == *card_id);
+        let card = chosen.pop().unwrap();
+        player.pending_first_action = !card.first_action.is_empty();
+        player.corporation = Some(card);
+        self.rejected = rejected.len();
         game_state.corporation_pile.discard_cards(rejected.as_mut());
         Ok(())
     }
 
     fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
-        // players always choose from 2 corporations
-        let mut coorps = vec![game_state.corporation_pile.discard_pile.pop().unwrap()];
+        let first_idx = game_state.corporation_pile.discard_pile.len() - self.rejected;
+        let mut coorps = game_state.corporation_pile.discard_pile.drain(first_idx..).collect::<Vec<Card>>();
         let player = game_state.get_player_mut(self.player_id)?;
         coorps.push(player.corporation.take().unwrap());
+        player.pending_first_action = false;
         player.hand.append(coorps.as_mut());
         Ok(())
     }
 }
 
+// players keep 2 of the 4 dealt preludes
+pub const KEPT_PRELUDES: usize = 2;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ChoosePreludes {
+    pub player_id: usize,
+    pub card_ids: Vec<String>,
+    // the number of preludes that were discarded
+    #[serde(skip)] pub rejected: usize,
+}
+
+impl Command<GameState> for ChoosePreludes {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        if game_state.phase != Phase::Setup {
+            return CannotExecute::new("Can only select preludes in setup phase!".to_owned());
+        }
+        if self.card_ids.len() != KEPT_PRELUDES {
+            return CannotExecute::new(format!("Player {} has to keep exactly {} preludes!", self.player_id, KEPT_PRELUDES));
+        }
+        let player = game_state.get_player_mut(self.player_id)?;
+        let card_ids = &self.card_ids;
+        if player.hand.iter().filter(|c| c.card_type == CardType::Prelude && card_ids.contains(&c.id)).count() != KEPT_PRELUDES {
+            return CannotExecute::new(format!("Preludes {:?} not found in player {}'s hand!", card_ids, self.player_id));
+        }
+        let (preludes, projects): (Vec<Card>, Vec<Card>) = player.hand.drain(..).partition(|c| c.card_type == CardType::Prelude);
+        player.hand = projects;
+        let (mut chosen, mut rejected): (Vec<Card>, Vec<Card>) = preludes.into_iter().partition(|c| card_ids.contains(&c.id));
+        player.preludes.append(chosen.as_mut());
+        self.rejected = rejected.len();
+        game_state.prelude_pile.discard_cards(rejected.as_mut());
+        Ok(())
+    }
+
+    fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
+        let first_idx = game_state.prelude_pile.discard_pile.len() - self.rejected;
+        let mut rejected = game_state.prelude_pile.discard_pile.drain(first_idx..).collect::<Vec<Card>>();
+        let player = game_state.get_player_mut(self.player_id)?;
+        let mut chosen = player.preludes.drain(..).collect::<Vec<Card>>();
+        player.hand.append(chosen.as_mut());
+        player.hand.append(rejected.as_mut());
+        Ok(())
+    }
+}
+
+// Puts a kept prelude into play, its effects are chained by the StateMachine.
+pub struct PlayPrelude{pub player_id: usize, pub card_id: String}
+
+impl Command<GameState> for PlayPrelude {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        let player = game_state.get_player_mut(self.player_id)?;
+        let card = match player.preludes.iter().position(|c| c.id == self.card_id) {
+            Some(idx) => player.preludes.remove(idx),
+            None => return CannotExecute::new(format!("Prelude {} not found in player {}'s preludes!", self.card_id, self.player_id)),
+        };
+        game_state.cards_in_play.push(OwnedCard{card: card, owner: self.player_id, resources: 0, used: false});
+        Ok(())
+    }
+
+    fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
+        let owned_card = match game_state.cards_in_play.iter().position(|c| c.card.id == self.card_id) {
+            Some(idx) => game_state.cards_in_play.remove(idx),
+            None => return CannotExecute::new(format!("Card {} not found in cards_in_play!", self.card_id)),
+        };
+        game_state.get_player_mut(self.player_id)?.preludes.push(owned_card.card);
+        Ok(())
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ResearchCards{pub player_id: usize, pub card_ids: Vec<String>}
 
@@ -156,6 +378,9 @@ const CARD_COST: u32 = 3;
 impl Command<GameState> for ResearchCards {
     fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
         let player = game_state.get_player_mut(self.player_id)?;
+        if !player.draft_hand.is_empty() {
+            return CannotExecute::new(format!("Cannot buy cards, player {} is still drafting!", self.player_id));
+        }
         // validate consistent ids in research_queue and card_ids
         if player.research_queue.iter().filter(|c| self.card_ids.contains(&c.id)).count() != self.card_ids.len() {
             return CannotExecute::new("card_ids and research_queue did not match".to_owned());
@@ -207,6 +432,94 @@ impl Command<GameState> for DiscardResearch {
     }
 }
 
+// deals projects into the player's draft hand instead of the research queue
+pub struct DealDraft{pub player_id: usize, pub count: usize}
+
+impl Command<GameState> for DealDraft {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        let mut cards = game_state.project_pile.draw_cards(self.count);
+        game_state.get_player_mut(self.player_id)?.draft_hand.append(cards.as_mut());
+        Ok(())
+    }
+
+    fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
+        let player = game_state.get_player_mut(self.player_id)?;
+        let draw_range = player.draft_hand.len()-self.count..;
+        let mut cards = player.draft_hand.drain(draw_range).rev().collect::<Vec<Card>>();
+        game_state.project_pile.draw_pile.append(cards.as_mut());
+        Ok(())
+    }
+}
+
+// Moves the card from the draft hand into the research queue. Every player picks one card per round,
+// the remaining cards are passed by the StateMachine once all players have picked.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DraftCard{pub player_id: usize, pub card_id: String, #[serde(skip)] pub index: Option<usize>}
+
+impl Command<GameState> for DraftCard {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        if game_state.phase != Phase::Setup && game_state.phase != Phase::Research {
+            return CannotExecute::new(format!("Cannot draft cards in Phase {:?}", game_state.phase));
+        }
+        let round_size = game_state.players.iter().map(|p| p.draft_hand.len()).max().unwrap_or(0);
+        let player = game_state.get_player_mut(self.player_id)?;
+        if player.draft_hand.len() < round_size {
+            return CannotExecute::new(format!("Player {} already drafted a card, waiting for the other players!", self.player_id));
+        }
+        let index = match player.draft_hand.iter().position(|c| c.id == self.card_id) {
+            Some(index) => index,
+            None => return CannotExecute::new(format!("Card {} not found in player {}'s draft hand!", self.card_id, self.player_id)),
+        };
+        let card = player.draft_hand.remove(index);
+        player.research_queue.push(card);
+        self.index = Some(index);
+        Ok(())
+    }
+
+    fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
+        let player = game_state.get_player_mut(self.player_id)?;
+        let card = player.research_queue.pop().unwrap();
+        player.draft_hand.insert(self.index.take().unwrap(), card);
+        Ok(())
+    }
+}
+
+// Passes the remaining draft hands to the next player, or to the previous one if not forward.
+// The last card of a draft is kept by the player who receives it.
+pub struct PassDraft{pub forward: bool}
+
+impl Command<GameState> for PassDraft {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        let mut hands: Vec<Vec<Card>> = game_state.players.iter_mut().map(|p| p.draft_hand.drain(..).collect()).collect();
+        match self.forward {
+            true => hands.rotate_right(1),
+            false => hands.rotate_left(1),
+        }
+        for (player, mut hand) in game_state.players.iter_mut().zip(hands) {
+            match hand.len() {
+                1 => player.research_queue.append(hand.as_mut()),
+                _ => player.draft_hand = hand,
+            }
+        }
+        Ok(())
+    }
+
+    fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
+        let mut hands: Vec<Vec<Card>> = game_state.players.iter_mut().map(|p| match p.draft_hand.is_empty() {
+            true => p.research_queue.pop().into_iter().collect(),
+            false => p.draft_hand.drain(..).collect(),
+        }).collect();
+        match self.forward {
+            true => hands.rotate_left(1),
+            false => hands.rotate_right(1),
+        }
+        for (player, hand) in game_state.players.iter_mut().zip(hands) {
+            player.draft_hand = hand;
+        }
+        Ok(())
+    }
+}
+
 pub struct ModResources{pub player_id: usize, pub rescs: Vec<Resource>}
 
 fn mod_inventory(inv: &mut u32, count: i32, player_id: usize, res_type: String) -> undo::Result {
@@ -218,10 +531,25 @@ fn mod_inventory(inv: &mut u32, count: i32, player_id: usize, res_type: String)
     }
 }
 
+// we can always undo
+fn restore_inventory(player: &mut Player, rescs: &[Resource]) {
+    for res in rescs.iter() {
+        match *res {
+            Resource::MegaCredits(count) => player.inventory.megacredits = (player.inventory.megacredits as i32 - count) as u32,
+            Resource::Steel(count) => player.inventory.steel = (player.inventory.steel as i32 - count) as u32,
+            Resource::Titanium(count) => player.inventory.titanium = (player.inventory.titanium as i32 - count) as u32,
+            Resource::Plants(count) => player.inventory.plants = (player.inventory.plants as i32 - count) as u32,
+            Resource::Energy(count) => player.inventory.energy = (player.inventory.energy as i32 - count) as u32,
+            Resource::Heat(count) => player.inventory.heat = (player.inventory.heat as i32 - count) as u32,
+            Resource::Special => continue, // TODO
+        };
+    }
+}
+
 impl Command<GameState> for ModResources {
     fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
         let player = game_state.get_player_mut(self.player_id)?;
-        for res in self.rescs.iter() {
+        for (idx, res) in self.rescs.iter().enumerate() {
             let result = match *res {
                 Resource::MegaCredits(count) => mod_inventory(&mut player.inventory.megacredits, count, player.id, "Megacredits".to_owned()),
                 Resource::Steel(count) => mod_inventory(&mut player.inventory.steel, count, player.id, "Steel".to_owned()),
@@ -231,28 +559,17 @@ impl Command<GameState> for ModResources {
                 Resource::Heat(count) => mod_inventory(&mut player.inventory.heat, count, player.id, "Heat".to_owned()),
                 Resource::Special => continue, // TODO
             };
-            match result {
-                Err(err) => return Err(err),
-                Ok(()) => ()
-            };
+            // the resources changed before are restored, a failing command leaves the state untouched
+            if let Err(err) = result {
+                restore_inventory(player, &self.rescs[..idx]);
+                return Err(err);
+            }
         }
         Ok(())
     }
 
     fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
-        let player = game_state.get_player_mut(self.player_id)?;
-        for res in self.rescs.iter() {
-            // we can always undo
-            match *res {
-                Resource::MegaCredits(count) => player.inventory.megacredits = (player.inventory.megacredits as i32 - count) as u32,
-                Resource::Steel(count) => player.inventory.steel = (player.inventory.steel as i32 - count) as u32,
-                Resource::Titanium(count) => player.inventory.titanium = (player.inventory.titanium as i32 - count) as u32,
-                Resource::Plants(count) => player.inventory.plants = (player.inventory.plants as i32 - count) as u32,
-                Resource::Energy(count) => player.inventory.energy = (player.inventory.energy as i32 - count) as u32,
-                Resource::Heat(count) => player.inventory.heat = (player.inventory.heat as i32 - count) as u32,
-                Resource::Special => continue, // TODO 
-            };
-        }
+        restore_inventory(game_state.get_player_mut(self.player_id)?, &self.rescs);
         Ok(())
     }
 }
@@ -268,16 +585,32 @@ fn mod_production(prod: &mut u32, count: i32, player_id: usize, res_type: String
     }
 }
 
+// we can always undo
+fn restore_production(player: &mut Player, rescs: &[Resource]) {
+    for res in rescs.iter() {
+        match *res {
+            // special case MegaCredits production can also be negative
+            Resource::MegaCredits(count) => player.production.megacredits -= count,
+            Resource::Steel(count) => player.production.steel = (player.production.steel as i32 - count) as u32,
+            Resource::Titanium(count) => player.production.titanium = (player.production.titanium as i32 - count) as u32,
+            Resource::Plants(count) => player.production.plants = (player.production.plants as i32 - count) as u32,
+            Resource::Energy(count) => player.production.energy = (player.production.energy as i32 - count) as u32,
+            Resource::Heat(count) => player.production.heat = (player.production.heat as i32 - count) as u32,
+            Resource::Special => continue, // TODO
+        };
+    }
+}
+
 impl Command<GameState> for ModProduction {
     fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
         let player = game_state.get_player_mut(self.player_id)?;
-        for res in self.rescs.iter() {
+        for (idx, res) in self.rescs.iter().enumerate() {
             // if res == Resource
             let result = match *res {
                 // special case MegeCredits can be down to -5 production
                 Resource::MegaCredits(count) => {
-                    if count + player.production.megacredits < -5 {
-                        return CannotExecute::new(format!("Insufficient Megacredits production! player {} needs {}", player.id, -5-count));
+                    if count + player.production.megacredits < MIN_MEGACREDIT_PRODUCTION {
+                        CannotExecute::new(format!("Insufficient Megacredits production! player {} needs {}", player.id, MIN_MEGACREDIT_PRODUCTION - count))
                     } else {
                         player.production.megacredits += count;
                         Ok(())
@@ -290,52 +623,1050 @@ impl Command<GameState> for ModProduction {
                 Resource::Heat(count) => mod_production(&mut player.production.heat, count, player.id, "Heat".to_owned()),
                 Resource::Special => continue, // TODO
             };
-            match result {
-                Err(err) => return Err(err),
-                Ok(()) => (),
-            };
+            // the productions changed before are restored, a failing command leaves the state untouched
+            if let Err(err) = result {
+                restore_production(player, &self.rescs[..idx]);
+                return Err(err);
+            }
         }
         Ok(())
     }
 
     fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
-        let player = game_state.get_player_mut(self.player_id)?;
-        for res in self.rescs.iter() {
-            // we can always undo
-            match *res {
-                // special case MegaCredits production can also be negative
-                Resource::MegaCredits(count) => player.production.megacredits -= count,
-                Resource::Steel(count) => player.production.steel = (player.production.steel as i32 - count) as u32,
-                Resource::Titanium(count) => player.production.titanium = (player.production.titanium as i32 - count) as u32,
-                Resource::Plants(count) => player.production.plants = (player.production.plants as i32 - count) as u32,
-                Resource::Energy(count) => player.production.energy = (player.production.energy as i32 - count) as u32,
-                Resource::Heat(count) => player.production.heat = (player.production.heat as i32 - count) as u32,
-                Resource::Special => continue, // TODO 
-            };
+        restore_production(game_state.get_player_mut(self.player_id)?, &self.rescs);
+        Ok(())
+    }
+}
+
+pub struct ModRating{pub player_id: usize, pub steps: i32}
+
+impl Command<GameState> for ModRating {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        game_state.get_player_mut(self.player_id)?.tf_rating += self.steps;
+        Ok(())
+    }
+
+    fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
+        game_state.get_player_mut(self.player_id)?.tf_rating -= self.steps;
+        Ok(())
+    }
+}
+
+// Adds or removes resources on a card in play. The card may belong to any player, e.g. Predators
+// remove animals from an opponent's card.
+pub struct ModCardResources{pub card_id: String, pub resource: HoldableResource, pub count: i32}
+
+impl Command<GameState> for ModCardResources {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        let owned_card = match game_state.cards_in_play.iter_mut().find(|c| c.card.id == self.card_id) {
+            Some(owned_card) => owned_card,
+            None => return CannotExecute::new(format!("Card {} not found in cards_in_play!", self.card_id)),
+        };
+        if owned_card.card.interactions.holds_resources != self.resource {
+            return CannotExecute::new(format!("Card {} cannot hold {:?}!", self.card_id, self.resource));
+        }
+        if self.count < 0 && self.count.abs() as u32 > owned_card.resources {
+            return CannotExecute::new(format!("Insufficient {:?}! Card {} holds only {}", self.resource, self.card_id, owned_card.resources));
+        }
+        owned_card.resources = (owned_card.resources as i32 + self.count) as u32;
+        Ok(())
+    }
+
+    fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
+        match game_state.cards_in_play.iter_mut().find(|c| c.card.id == self.card_id) {
+            Some(owned_card) => owned_card.resources = (owned_card.resources as i32 - self.count) as u32,
+            None => return CannotExecute::new(format!("Card {} not found in cards_in_play!", self.card_id)),
         }
         Ok(())
     }
 }
 
+// the player has to discard the cards with DiscardCards
+pub struct QueueDiscard{pub player_id: usize, pub count: u32}
+
+impl Command<GameState> for QueueDiscard {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        game_state.get_player_mut(self.player_id)?.pending_discards += self.count;
+        Ok(())
+    }
+
+    fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
+        game_state.get_player_mut(self.player_id)?.pending_discards -= self.count;
+        Ok(())
+    }
+}
+
+// Discards pending cards from the hand. Colonists may discard outside of their turn.
 #[derive(Debug, Deserialize, Serialize)]
-pub enum CmdWrapper {
-    PlayCard(PlayCard),
-    ResearchCards(ResearchCards),
-    ChooseCorporation(ChooseCorporation),
+pub struct DiscardCards{pub player_id: usize, pub card_ids: Vec<String>}
+
+impl Command<GameState> for DiscardCards {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        let player = game_state.get_player_mut(self.player_id)?;
+        if self.card_ids.is_empty() || self.card_ids.len() as u32 > player.pending_discards {
+            return CannotExecute::new(format!("Player {} has to discard {} cards, not {}!", self.player_id, player.pending_discards, self.card_ids.len()));
+        }
+        if player.hand.iter().filter(|c| self.card_ids.contains(&c.id)).count() != self.card_ids.len() {
+            return CannotExecute::new("card_ids and hand did not match".to_owned());
+        }
+        let (mut discarded, mut kept): (Vec<Card>, Vec<Card>) = player.hand.drain(..).partition(|c| self.card_ids.contains(&c.id));
+        player.hand.append(kept.as_mut());
+        player.pending_discards -= discarded.len() as u32;
+        game_state.project_pile.discard_cards(discarded.as_mut());
+        Ok(())
+    }
+
+    fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
+        let first_idx = game_state.project_pile.discard_pile.len() - self.card_ids.len();
+        let mut cards = game_state.project_pile.discard_pile.drain(first_idx..).collect::<Vec<Card>>();
+        let player = game_state.get_player_mut(self.player_id)?;
+        player.pending_discards += cards.len() as u32;
+        player.hand.append(cards.as_mut());
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
-pub struct CannotExecute{pub reason: String}
+// enqueues a tile the player has to place with PlaceTile
+pub struct QueueTile{pub player_id: usize, pub tile: TilePlacement}
 
-impl CannotExecute {
-    pub fn new(reason: String) -> undo::Result {
-        Err(Box::new(CannotExecute{reason: reason}))
+impl Command<GameState> for QueueTile {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        game_state.get_player_mut(self.player_id)?.pending_tiles.push(self.tile.to_owned());
+        Ok(())
+    }
+
+    fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
+        game_state.get_player_mut(self.player_id)?.pending_tiles.pop();
+        Ok(())
     }
 }
 
-impl fmt::Display for CannotExecute {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.reason)
+// Places the player's first pending tile at (x, z), x and z are ignored for tiles off mars.
+// Placing the last tile of the player's last action hands the turn to the next player.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PlaceTile{
+    pub player_id: usize,
+    pub x: u32,
+    pub z: u32,
+    #[serde(skip)] pub placed: Option<TilePlacement>,
+    #[serde(skip)] pub turn: Option<Turn>,
+}
+
+impl Command<GameState> for PlaceTile {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        // tiles are placed in the player's own turn, only the final greeneries are placed in any order
+        if game_state.phase != Phase::FinalGreenery {
+            check_action(game_state, self.player_id, "place a tile")?;
+        }
+        let tile = match game_state.get_player(self.player_id)?.pending_tiles.first() {
+            Some(tile) => tile.to_owned(),
+            None => return CannotExecute::new(format!("Player {} has no tile to place!", self.player_id)),
+        };
+        // oceans are not owned by anybody
+        let owner = match tile.tile_type {
+            TileType::Ocean => None,
+            _ => Some(self.player_id),
+        };
+        match &tile.placement {
+            Placement::OffMars(name) => {
+                let special_tile = match game_state.special_tiles.iter_mut().find(|t| t.name == *name) {
+                    Some(special_tile) => special_tile,
+                    None => return CannotExecute::new(format!("Cannot find special tile {}!", name)),
+                };
+                special_tile.tile_type = tile.tile_type.to_owned();
+                special_tile.owner = owner;
+            }
+            _ => {
+                board::check_placement(game_state, self.player_id, &tile, self.x, self.z)?;
+                let space = game_state.tiles.iter_mut().find(|t| t.x == self.x && t.z == self.z).unwrap();
+                space.tile_type = tile.tile_type.to_owned();
+                space.owner = owner;
+            }
+        }
+        let player = game_state.get_player_mut(self.player_id)?;
+        player.pending_tiles.remove(0);
+        let turn_over = player.pending_tiles.is_empty() && player.action_state == ActionState::Acting(0);
+        self.placed = Some(tile);
+        if turn_over {
+            self.turn = Some(save_turn(game_state));
+            game_state.players[self.player_id].action_state = ActionState::Waiting;
+            next_turn(game_state);
+        }
+        Ok(())
+    }
+
+    fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
+        let tile = match self.placed.take() {
+            Some(tile) => tile,
+            None => return CannotExecute::new("Cannot undo PlaceTile, no tile was placed!".to_owned()),
+        };
+        match &tile.placement {
+            Placement::OffMars(name) => {
+                let special_tile = game_state.special_tiles.iter_mut().find(|t| t.name == *name).unwrap();
+                special_tile.tile_type = TileType::Empty;
+                special_tile.owner = None;
+            }
+            _ => {
+                let space = game_state.tiles.iter_mut().find(|t| t.x == self.x && t.z == self.z).unwrap();
+                space.tile_type = TileType::Empty;
+                space.owner = None;
+            }
+        }
+        game_state.get_player_mut(self.player_id)?.pending_tiles.insert(0, tile);
+        match self.turn.is_some() {
+            true => restore_turn(game_state, self.turn.take()),
+            false => Ok(()),
+        }
+    }
+}
+
+// raises a global parameter and the player's TR by the same number of steps
+pub struct RaiseParameter{pub player_id: usize, pub parameter: GlobalParameter, pub steps: u32}
+
+impl Command<GameState> for RaiseParameter {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        match self.parameter {
+            GlobalParameter::Temperature => {
+                let temperature = game_state.temperature + self.steps as i32 * TEMPERATURE_STEP;
+                if temperature > MAX_TEMPERATURE {
+                    return CannotExecute::new(format!("Cannot raise temperature above {}°C!", MAX_TEMPERATURE));
+                }
+                game_state.temperature = temperature;
+            }
+            GlobalParameter::Oxygen => {
+                if game_state.oxygen + self.steps > MAX_OXYGEN {
+                    return CannotExecute::new(format!("Cannot raise oxygen above {}%!", MAX_OXYGEN));
+                }
+                game_state.oxygen += self.steps;
+            }
+            GlobalParameter::Ocean => {
+                if game_state.oceans_placed + self.steps > MAX_OCEANS {
+                    return CannotExecute::new(format!("Cannot place more than {} oceans!", MAX_OCEANS));
+                }
+                game_state.oceans_placed += self.steps;
+            }
+            GlobalParameter::Venus => {
+                let venus = game_state.venus + self.steps * VENUS_STEP;
+                if venus > MAX_VENUS {
+                    return CannotExecute::new(format!("Cannot raise venus above {}%!", MAX_VENUS));
+                }
+                game_state.venus = venus;
+            }
+        }
+        game_state.get_player_mut(self.player_id)?.tf_rating += self.steps as i32;
+        Ok(())
+    }
+
+    fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
+        match self.parameter {
+            GlobalParameter::Temperature => game_state.temperature -= self.steps as i32 * TEMPERATURE_STEP,
+            GlobalParameter::Oxygen => game_state.oxygen -= self.steps,
+            GlobalParameter::Ocean => game_state.oceans_placed -= self.steps,
+            GlobalParameter::Venus => game_state.venus -= self.steps * VENUS_STEP,
+        }
+        game_state.get_player_mut(self.player_id)?.tf_rating -= self.steps as i32;
+        Ok(())
+    }
+}
+
+pub const POWER_PLANT_COST: u32 = 11;
+pub const ASTEROID_COST: u32 = 14;
+pub const AQUIFER_COST: u32 = 18;
+pub const GREENERY_COST: u32 = 23;
+pub const CITY_COST: u32 = 25;
+// only with venus next
+pub const AIR_SCRAPPING_COST: u32 = 15;
+
+// Standard projects only validate that the player may act. The costs and effects are chained by the StateMachine.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StandardProject{pub player_id: usize}
+
+impl Command<GameState> for StandardProject {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        check_action(game_state, self.player_id, "fund a standard project")
+    }
+
+    fn undo(&mut self, _game_state: &mut GameState) -> undo::Result {
+        Ok(())
+    }
+}
+
+// discards any number of cards from the hand for 1 Megacredit each
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SellPatents{pub player_id: usize, pub card_ids: Vec<String>}
+
+impl Command<GameState> for SellPatents {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        check_action(game_state, self.player_id, "SellPatents")?;
+        let player = game_state.get_player_mut(self.player_id)?;
+        if self.card_ids.is_empty() || player.hand.iter().filter(|c| self.card_ids.contains(&c.id)).count() != self.card_ids.len() {
+            return CannotExecute::new("card_ids and hand did not match".to_owned());
+        }
+        let (mut sold, mut kept): (Vec<Card>, Vec<Card>) = player.hand.drain(..).partition(|c| self.card_ids.contains(&c.id));
+        player.hand.append(kept.as_mut());
+        player.inventory.megacredits += sold.len() as u32;
+        game_state.project_pile.discard_cards(sold.as_mut());
+        Ok(())
+    }
+
+    fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
+        let first_idx = game_state.project_pile.discard_pile.len() - self.card_ids.len();
+        let mut cards = game_state.project_pile.discard_pile.drain(first_idx..).collect::<Vec<Card>>();
+        let player = game_state.get_player_mut(self.player_id)?;
+        player.inventory.megacredits -= cards.len() as u32;
+        player.hand.append(cards.as_mut());
+        Ok(())
+    }
+}
+
+// Conversions only validate that the player may convert. The resources are spent by the StateMachine.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ConvertPlants{pub player_id: usize}
+
+impl Command<GameState> for ConvertPlants {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        // in the final greenery round all players may convert in any order until they pass
+        if game_state.phase == Phase::FinalGreenery {
+            if game_state.get_player(self.player_id)?.action_state == ActionState::Passed {
+                return CannotExecute::new(format!("Player {} is already done with the final greeneries!", self.player_id));
+            }
+            return Ok(());
+        }
+        check_action(game_state, self.player_id, "convert plants")
+    }
+
+    fn undo(&mut self, _game_state: &mut GameState) -> undo::Result {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ConvertHeat{pub player_id: usize}
+
+impl Command<GameState> for ConvertHeat {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        check_action(game_state, self.player_id, "convert heat")?;
+        if game_state.temperature >= MAX_TEMPERATURE {
+            return CannotExecute::new("Cannot convert heat, temperature is already at its maximum!".to_owned());
+        }
+        Ok(())
+    }
+
+    fn undo(&mut self, _game_state: &mut GameState) -> undo::Result {
+        Ok(())
+    }
+}
+
+// the turn order before a command changed it, so it can be restored on undo
+#[derive(Debug)]
+pub struct Turn{active_player: usize, action_states: Vec<ActionState>}
+
+fn save_turn(game_state: &GameState) -> Turn {
+    Turn {
+        active_player: game_state.active_player,
+        action_states: game_state.players.iter().map(|p| p.action_state.to_owned()).collect(),
+    }
+}
+
+fn restore_turn(game_state: &mut GameState, turn: Option<Turn>) -> undo::Result {
+    let turn = match turn {
+        Some(turn) => turn,
+        None => return CannotExecute::new("Cannot undo, the turn order was not saved!".to_owned()),
+    };
+    game_state.active_player = turn.active_player;
+    for (player, action_state) in game_state.players.iter_mut().zip(turn.action_states.into_iter()) {
+        player.action_state = action_state;
+    }
+    Ok(())
+}
+
+// hands the turn to the next player in seat order who has not passed yet
+fn next_turn(game_state: &mut GameState) {
+    let player_count = game_state.players.len();
+    for offset in 1..=player_count {
+        let id = (game_state.active_player + offset) % player_count;
+        if game_state.players[id].action_state != ActionState::Passed {
+            game_state.players[id].action_state = ActionState::Acting(ACTIONS_PER_TURN);
+            game_state.active_player = id;
+            return;
+        }
+    }
+}
+
+fn check_turn(game_state: &GameState, player_id: usize, action: &str) -> Result<u8, CannotExecute> {
+    if game_state.phase != Phase::Action || player_id != game_state.active_player {
+        return Err(CannotExecute{reason: format!("Player {} cannot {}, it is not their turn!", player_id, action)});
+    }
+    match game_state.get_player(player_id)?.action_state {
+        ActionState::Acting(actions) => Ok(actions),
+        _ => Err(CannotExecute{reason: format!("Player {} cannot {}, they are not acting!", player_id, action)}),
+    }
+}
+
+// Counts an action of the active player, the turn ends after the last action.
+// If the action left tiles to place, the turn ends once they are placed.
+pub struct UseAction{pub player_id: usize, pub turn: Option<Turn>}
+
+impl Command<GameState> for UseAction {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        let actions = check_turn(game_state, self.player_id, "take an action")?;
+        if actions == 0 {
+            return CannotExecute::new(format!("Player {} has no actions left!", self.player_id));
+        }
+        self.turn = Some(save_turn(game_state));
+        if actions == 1 && !game_state.players[self.player_id].pending_tiles.is_empty() {
+            game_state.players[self.player_id].action_state = ActionState::Acting(0);
+        } else if actions == 1 {
+            game_state.players[self.player_id].action_state = ActionState::Waiting;
+            next_turn(game_state);
+        } else {
+            game_state.players[self.player_id].action_state = ActionState::Acting(actions - 1);
+        }
+        Ok(())
+    }
+
+    fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
+        restore_turn(game_state, self.turn.take())
+    }
+}
+
+// ends the turn after the first action, without any action the player has to pass
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EndTurn{pub player_id: usize, #[serde(skip)] pub turn: Option<Turn>}
+
+impl Command<GameState> for EndTurn {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        if check_turn(game_state, self.player_id, "end the turn")? == ACTIONS_PER_TURN {
+            return CannotExecute::new(format!("Player {} has to take an action or pass!", self.player_id));
+        }
+        self.turn = Some(save_turn(game_state));
+        game_state.players[self.player_id].action_state = ActionState::Waiting;
+        next_turn(game_state);
+        Ok(())
+    }
+
+    fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
+        restore_turn(game_state, self.turn.take())
+    }
+}
+
+// a player who passed takes no more actions in this generation, or places no more final greeneries
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Pass{pub player_id: usize, #[serde(skip)] pub turn: Option<Turn>}
+
+impl Command<GameState> for Pass {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        // in the final greenery round passing means being done with the greeneries
+        if game_state.phase == Phase::FinalGreenery {
+            let player = game_state.get_player(self.player_id)?;
+            if player.action_state == ActionState::Passed {
+                return CannotExecute::new(format!("Player {} is already done with the final greeneries!", self.player_id));
+            }
+            if !player.pending_tiles.is_empty() {
+                return CannotExecute::new(format!("Player {} has to place their pending tiles first!", self.player_id));
+            }
+            self.turn = Some(save_turn(game_state));
+            game_state.players[self.player_id].action_state = ActionState::Passed;
+            return Ok(());
+        }
+        check_turn(game_state, self.player_id, "pass")?;
+        self.turn = Some(save_turn(game_state));
+        game_state.players[self.player_id].action_state = ActionState::Passed;
+        next_turn(game_state);
+        Ok(())
+    }
+
+    fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
+        restore_turn(game_state, self.turn.take())
+    }
+}
+
+pub const MILESTONE_COST: u32 = 8;
+pub const MAX_MILESTONES: usize = 3;
+pub const AWARD_COSTS: [u32; 3] = [8, 14, 20];
+
+// The milestone and award commands only check the conditions and set the owner.
+// The costs are chained by the StateMachine.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ClaimMilestone{pub player_id: usize, pub milestone: Milestones}
+
+impl Command<GameState> for ClaimMilestone {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        check_action(game_state, self.player_id, "claim a milestone")?;
+        if game_state.milestones.iter().filter(|m| m.owner.is_some()).count() >= MAX_MILESTONES {
+            return CannotExecute::new(format!("Cannot claim {:?}, {} milestones are already claimed!", self.milestone, MAX_MILESTONES));
+        }
+        let progress = self.milestone.progress(game_state, self.player_id);
+        if progress < self.milestone.requirement() {
+            return CannotExecute::new(format!("Cannot claim {:?}, player {} has {} of {}!", self.milestone, self.player_id, progress, self.milestone.requirement()));
+        }
+        let milestone = match game_state.milestones.iter_mut().find(|m| m.name == self.milestone) {
+            Some(milestone) => milestone,
+            None => return CannotExecute::new(format!("Milestone {:?} is not part of this game!", self.milestone)),
+        };
+        if let Some(owner) = milestone.owner {
+            return CannotExecute::new(format!("Milestone {:?} is already claimed by player {}!", self.milestone, owner));
+        }
+        milestone.owner = Some(self.player_id);
+        Ok(())
+    }
+
+    fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
+        game_state.milestones.iter_mut().find(|m| m.name == self.milestone).unwrap().owner = None;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FundAward{pub player_id: usize, pub award: Awards}
+
+impl Command<GameState> for FundAward {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        check_action(game_state, self.player_id, "fund an award")?;
+        if game_state.awards.iter().filter(|a| a.owner.is_some()).count() >= AWARD_COSTS.len() {
+            return CannotExecute::new(format!("Cannot fund {:?}, {} awards are already funded!", self.award, AWARD_COSTS.len()));
+        }
+        let award = match game_state.awards.iter_mut().find(|a| a.name == self.award) {
+            Some(award) => award,
+            None => return CannotExecute::new(format!("Award {:?} is not part of this game!", self.award)),
+        };
+        if let Some(owner) = award.owner {
+            return CannotExecute::new(format!("Award {:?} is already funded by player {}!", self.award, owner));
+        }
+        award.owner = Some(self.player_id);
+        Ok(())
+    }
+
+    fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
+        game_state.awards.iter_mut().find(|a| a.name == self.award).unwrap().owner = None;
+        Ok(())
+    }
+}
+
+// Marks the card as used in this generation, the action's effects are chained by the StateMachine.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UseCardAction{
+    pub player_id: usize,
+    pub card_id: String,
+    pub target_id: Option<usize>,
+    pub target_card: Option<String>,
+    pub choice: Option<usize>,
+    // without a payment the action is paid with megacredits only
+    pub payment: Option<Payment>,
+}
+
+impl Command<GameState> for UseCardAction {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        check_action(game_state, self.player_id, "use a card action")?;
+        let owned_card = match game_state.cards_in_play.iter_mut().find(|c| c.card.id == self.card_id && c.owner == self.player_id) {
+            Some(owned_card) => owned_card,
+            None => return CannotExecute::new(format!("Card {} is not in play for player {}!", self.card_id, self.player_id)),
+        };
+        if owned_card.card.action.is_empty() {
+            return CannotExecute::new(format!("Card {} has no action!", self.card_id));
+        }
+        if owned_card.used {
+            return CannotExecute::new(format!("The action of card {} was already used this generation!", self.card_id));
+        }
+        owned_card.used = true;
+        Ok(())
+    }
+
+    fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
+        match game_state.cards_in_play.iter_mut().find(|c| c.card.id == self.card_id) {
+            Some(owned_card) => owned_card.used = false,
+            None => return CannotExecute::new(format!("Card {} not found in cards_in_play!", self.card_id)),
+        }
+        Ok(())
+    }
+}
+
+pub const BUILD_COLONY_COST: u32 = 17;
+
+// Places a colony of the player on the colony tile, the track marker is pushed past the colonies.
+// Used by the Build Colony standard project and by cards, the bonus is chained by the StateMachine.
+pub struct PlaceColony{pub player_id: usize, pub colony: Colonies, pub duplicate: bool, pub track: Option<u32>}
+
+impl Command<GameState> for PlaceColony {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        let active = match colony::get_colony(&game_state.colonies, &self.colony) {
+            Some(tile) => tile.is_active(game_state),
+            None => return CannotExecute::new(format!("Colony {:?} is not part of this game!", self.colony)),
+        };
+        if !active {
+            return CannotExecute::new(format!("Colony {:?} is not active yet!", self.colony));
+        }
+        let tile = game_state.colonies.iter_mut().find(|c| c.name == self.colony).unwrap();
+        if tile.colonies.len() >= MAX_COLONIES {
+            return CannotExecute::new(format!("Colony {:?} has no room for another colony!", self.colony));
+        }
+        if !self.duplicate && tile.colonies.contains(&self.player_id) {
+            return CannotExecute::new(format!("Player {} already has a colony on {:?}!", self.player_id, self.colony));
+        }
+        self.track = Some(tile.track);
+        tile.colonies.push(self.player_id);
+        tile.track = tile.track.max(tile.min_track());
+        Ok(())
+    }
+
+    fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
+        let tile = game_state.colonies.iter_mut().find(|c| c.name == self.colony).unwrap();
+        tile.colonies.pop();
+        tile.track = self.track.take().unwrap();
+        Ok(())
+    }
+}
+
+// The standard project, the target card receives the placement bonus of Miranda, Titan or Enceladus.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BuildColony{pub player_id: usize, pub colony: Colonies, pub target_card: Option<String>}
+
+impl Command<GameState> for BuildColony {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        check_action(game_state, self.player_id, "build a colony")?;
+        if !game_state.use_colonies {
+            return CannotExecute::new("Colonies are only available with the Colonies expansion!".to_owned());
+        }
+        Ok(())
+    }
+
+    fn undo(&mut self, _game_state: &mut GameState) -> undo::Result {
+        Ok(())
+    }
+}
+
+// Docks one of the player's trade fleets at the colony tile and moves the track marker back
+// to the first free colony space. The payment and the income are chained by the StateMachine.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Trade{
+    pub player_id: usize,
+    pub colony: Colonies,
+    pub payment: TradePayment,
+    pub target_card: Option<String>,
+    #[serde(skip)] pub track: Option<u32>,
+}
+
+impl Command<GameState> for Trade {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        check_action(game_state, self.player_id, "trade")?;
+        let docked = game_state.colonies.iter().filter(|c| c.trader == Some(self.player_id)).count() as u32;
+        let fleets = game_state.trade_fleets(self.player_id);
+        if docked >= fleets {
+            return CannotExecute::new(format!("Player {} has no trade fleet left, all {} are docked!", self.player_id, fleets));
+        }
+        let active = match colony::get_colony(&game_state.colonies, &self.colony) {
+            Some(tile) => tile.is_active(game_state),
+            None => return CannotExecute::new(format!("Colony {:?} is not part of this game!", self.colony)),
+        };
+        if !active {
+            return CannotExecute::new(format!("Colony {:?} is not active yet!", self.colony));
+        }
+        let tile = game_state.colonies.iter_mut().find(|c| c.name == self.colony).unwrap();
+        if let Some(trader) = tile.trader {
+            return CannotExecute::new(format!("Player {} already trades with {:?}!", trader, self.colony));
+        }
+        self.track = Some(tile.track);
+        tile.trader = Some(self.player_id);
+        tile.track = tile.min_track();
+        Ok(())
+    }
+
+    fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
+        let tile = game_state.colonies.iter_mut().find(|c| c.name == self.colony).unwrap();
+        tile.trader = None;
+        tile.track = self.track.take().unwrap();
+        Ok(())
+    }
+}
+
+// Takes the first action of the player's corporation, e.g. Tharsis Republic placing a city.
+// The effects are chained by the StateMachine.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FirstAction{pub player_id: usize, pub colony: Option<Colonies>}
+
+impl Command<GameState> for FirstAction {
+    fn apply(&mut self, game_state: &mut GameState) -> undo::Result {
+        check_action(game_state, self.player_id, "take the first action")?;
+        let player = game_state.get_player_mut(self.player_id)?;
+        if !player.pending_first_action {
+            return CannotExecute::new(format!("Player {} has no first action to take!", self.player_id));
+        }
+        player.pending_first_action = false;
+        Ok(())
+    }
+
+    fn undo(&mut self, game_state: &mut GameState) -> undo::Result {
+        game_state.get_player_mut(self.player_id)?.pending_first_action = true;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub enum CmdWrapper {
+    PlayCard(PlayCard),
+    ResearchCards(ResearchCards),
+    ChooseCorporation(ChooseCorporation),
+    PlaceTile(PlaceTile),
+    SellPatents(SellPatents),
+    PowerPlant(StandardProject),
+    Asteroid(StandardProject),
+    Aquifer(StandardProject),
+    Greenery(StandardProject),
+    City(StandardProject),
+    AirScrapping(StandardProject),
+    BuildColony(BuildColony),
+    Trade(Trade),
+    ConvertPlants(ConvertPlants),
+    ConvertHeat(ConvertHeat),
+    EndTurn(EndTurn),
+    Pass(Pass),
+    ClaimMilestone(ClaimMilestone),
+    FundAward(FundAward),
+    UseCardAction(UseCardAction),
+    ChoosePreludes(ChoosePreludes),
+    FirstAction(FirstAction),
+    DraftCard(DraftCard),
+    DiscardCards(DiscardCards),
+}
+
+impl CmdWrapper {
+    // the player who sends the command
+    pub fn player_id(&self) -> usize {
+        match self {
+            CmdWrapper::PlayCard(cmd) => cmd.owner_id,
+            CmdWrapper::ResearchCards(cmd) => cmd.player_id,
+            CmdWrapper::ChooseCorporation(cmd) => cmd.player_id,
+            CmdWrapper::PlaceTile(cmd) => cmd.player_id,
+            CmdWrapper::SellPatents(cmd) => cmd.player_id,
+            CmdWrapper::PowerPlant(cmd) | CmdWrapper::Asteroid(cmd) | CmdWrapper::Aquifer(cmd) |
+            CmdWrapper::Greenery(cmd) | CmdWrapper::City(cmd) | CmdWrapper::AirScrapping(cmd) => cmd.player_id,
+            CmdWrapper::BuildColony(cmd) => cmd.player_id,
+            CmdWrapper::Trade(cmd) => cmd.player_id,
+            CmdWrapper::ConvertPlants(cmd) => cmd.player_id,
+            CmdWrapper::ConvertHeat(cmd) => cmd.player_id,
+            CmdWrapper::EndTurn(cmd) => cmd.player_id,
+            CmdWrapper::Pass(cmd) => cmd.player_id,
+            CmdWrapper::ClaimMilestone(cmd) => cmd.player_id,
+            CmdWrapper::FundAward(cmd) => cmd.player_id,
+            CmdWrapper::UseCardAction(cmd) => cmd.player_id,
+            CmdWrapper::ChoosePreludes(cmd) => cmd.player_id,
+            CmdWrapper::FirstAction(cmd) => cmd.player_id,
+            CmdWrapper::DraftCard(cmd) => cmd.player_id,
+            CmdWrapper::DiscardCards(cmd) => cmd.player_id,
+        }
+    }
+
+    // the player whose action in the action phase this command is
+    pub fn acting_player(&self) -> Option<usize> {
+        match self {
+            CmdWrapper::PlayCard(cmd) => Some(cmd.owner_id),
+            CmdWrapper::SellPatents(cmd) => Some(cmd.player_id),
+            CmdWrapper::PowerPlant(cmd) | CmdWrapper::Asteroid(cmd) | CmdWrapper::Aquifer(cmd) |
+            CmdWrapper::Greenery(cmd) | CmdWrapper::City(cmd) | CmdWrapper::AirScrapping(cmd) => Some(cmd.player_id),
+            CmdWrapper::BuildColony(cmd) => Some(cmd.player_id),
+            CmdWrapper::Trade(cmd) => Some(cmd.player_id),
+            CmdWrapper::ConvertPlants(cmd) => Some(cmd.player_id),
+            CmdWrapper::ConvertHeat(cmd) => Some(cmd.player_id),
+            CmdWrapper::ClaimMilestone(cmd) => Some(cmd.player_id),
+            CmdWrapper::FundAward(cmd) => Some(cmd.player_id),
+            CmdWrapper::UseCardAction(cmd) => Some(cmd.player_id),
+            CmdWrapper::FirstAction(cmd) => Some(cmd.player_id),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CannotExecute{pub reason: String}
+
+impl CannotExecute {
+    pub fn new(reason: String) -> undo::Result {
+        Err(Box::new(CannotExecute{reason: reason}))
+    }
+}
+
+impl fmt::Display for CannotExecute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.reason)
+    }
+}
+impl Error for CannotExecute {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::{STEEL_VALUE, TITANIUM_VALUE};
+    use crate::card::Deck;
+    use crate::test_utils::{action_game, basic_game, find_card, give_card, put_in_play};
+
+    #[test]
+    fn transaction_undoes_the_applied_commands_if_one_fails() {
+        let (mut state, _) = basic_game(2);
+        state.players[0].inventory.megacredits = 5;
+        let mut transaction = Transaction::new()
+            .join(ModResources{player_id: 0, rescs: vec![Resource::MegaCredits(3)]})
+            .join(ModRating{player_id: 0, steps: 1})
+            .join(ModResources{player_id: 0, rescs: vec![Resource::MegaCredits(-20)]});
+        assert!(transaction.apply(&mut state).is_err());
+        assert_eq!(state.players[0].inventory.megacredits, 5);
+        assert_eq!(state.players[0].tf_rating, 20);
+    }
+
+    #[test]
+    fn mod_resources_changes_nothing_if_one_resource_is_missing() {
+        let (mut state, _) = basic_game(2);
+        let mut command = ModResources{player_id: 0, rescs: vec![Resource::MegaCredits(2), Resource::Steel(-1)]};
+        assert!(command.apply(&mut state).is_err());
+        assert_eq!(state.players[0].inventory.megacredits, 0);
+        assert_eq!(state.players[0].inventory.steel, 0);
+    }
+
+    #[test]
+    fn raising_a_parameter_raises_the_rating_and_is_undone() {
+        let (mut state, _) = basic_game(2);
+        let mut command = RaiseParameter{player_id: 0, parameter: GlobalParameter::Oxygen, steps: 2};
+        command.apply(&mut state).unwrap();
+        assert_eq!(state.oxygen, 2);
+        assert_eq!(state.players[0].tf_rating, 22);
+        command.undo(&mut state).unwrap();
+        assert_eq!(state.oxygen, 0);
+        assert_eq!(state.players[0].tf_rating, 20);
+    }
+
+    #[test]
+    fn parameters_are_not_raised_beyond_their_maximum() {
+        let (mut state, _) = basic_game(2);
+        state.temperature = MAX_TEMPERATURE - TEMPERATURE_STEP;
+        let mut command = RaiseParameter{player_id: 0, parameter: GlobalParameter::Temperature, steps: 2};
+        assert!(command.apply(&mut state).is_err());
+        assert_eq!(state.temperature, MAX_TEMPERATURE - TEMPERATURE_STEP);
+        assert_eq!(state.players[0].tf_rating, 20);
+    }
+
+    #[test]
+    fn selling_patents_pays_one_megacredit_per_card_and_is_undone() {
+        let (mut state, cards) = basic_game(2);
+        let first = give_card(&mut state, &cards, 0, "Power Plant");
+        let second = give_card(&mut state, &cards, 0, "Lichen");
+        give_card(&mut state, &cards, 0, "Comet");
+        let mut command = SellPatents{player_id: 0, card_ids: vec![first, second]};
+        command.apply(&mut state).unwrap();
+        assert_eq!(state.players[0].inventory.megacredits, 2);
+        assert_eq!(state.players[0].hand.len(), 1);
+        assert_eq!(state.project_pile.discard_pile.len(), 2);
+        command.undo(&mut state).unwrap();
+        assert_eq!(state.players[0].inventory.megacredits, 0);
+        assert_eq!(state.players[0].hand.len(), 3);
+        assert!(state.project_pile.discard_pile.is_empty());
+    }
+
+    #[test]
+    fn only_cards_in_the_hand_are_sold() {
+        let (mut state, cards) = basic_game(2);
+        give_card(&mut state, &cards, 0, "Power Plant");
+        let mut command = SellPatents{player_id: 0, card_ids: vec!["missing".to_owned()]};
+        assert!(command.apply(&mut state).is_err());
+        assert!(SellPatents{player_id: 0, card_ids: vec![]}.apply(&mut state).is_err());
+        assert_eq!(state.players[0].hand.len(), 1);
+    }
+
+    fn pay(megacredits: u32, steel: u32, titanium: u32) -> Payment {
+        Payment{megacredits, steel, titanium}
+    }
+
+    #[test]
+    fn steel_pays_for_building_cards_only() {
+        let (mut state, cards) = basic_game(2);
+        state.players[0].inventory.megacredits = 10;
+        state.players[0].inventory.steel = 10;
+        let rover_construction = find_card(&cards, "Rover Construction");
+        let lichen = find_card(&cards, "Lichen");
+        assert!(check_payment(&rover_construction, &pay(2, 3, 0), 0, &state).is_ok());
+        assert!(check_payment(&rover_construction, &pay(0, 4, 0), 0, &state).is_ok());
+        assert!(check_payment(&lichen, &pay(5, 1, 0), 0, &state).is_err());
+    }
+
+    #[test]
+    fn titanium_pays_for_space_cards_only() {
+        let (mut state, cards) = basic_game(2);
+        state.players[0].inventory.megacredits = 10;
+        state.players[0].inventory.titanium = 10;
+        let asteroid = find_card(&cards, "Asteroid");
+        assert!(check_payment(&asteroid, &pay(2, 0, 4), 0, &state).is_ok());
+        assert!(check_payment(&find_card(&cards, "Rover Construction"), &pay(5, 0, 1), 0, &state).is_err());
+    }
+
+    #[test]
+    fn steel_and_titanium_may_overpay_but_no_unit_is_superfluous() {
+        let (mut state, cards) = basic_game(2);
+        state.players[0].inventory.megacredits = 10;
+        state.players[0].inventory.titanium = 10;
+        let asteroid = find_card(&cards, "Asteroid");
+        // 15 for 14 Megacredits, there is no exact way to pay with titanium only
+        assert!(check_payment(&asteroid, &pay(0, 0, 5), 0, &state).is_ok());
+        assert!(check_payment(&asteroid, &pay(3, 0, 4), 0, &state).is_err());
+        assert!(check_payment(&asteroid, &pay(2, 0, 5), 0, &state).is_err());
+        assert!(check_payment(&asteroid, &pay(1, 0, 4), 0, &state).is_err());
+    }
+
+    #[test]
+    fn payments_are_limited_by_the_inventory() {
+        let (mut state, cards) = basic_game(2);
+        state.players[0].inventory.megacredits = 2;
+        state.players[0].inventory.steel = 2;
+        let rover_construction = find_card(&cards, "Rover Construction");
+        assert!(check_payment(&rover_construction, &pay(2, 3, 0), 0, &state).is_err());
+    }
+
+    #[test]
+    fn advanced_alloys_and_phobolog_raise_the_metal_values() {
+        let (mut state, cards) = basic_game(2);
+        state.players[0].inventory.megacredits = 10;
+        state.players[0].inventory.steel = 10;
+        state.players[0].inventory.titanium = 10;
+        state.players[0].corporation = Some(find_card(&cards, "PhoboLog"));
+        let asteroid = find_card(&cards, "Asteroid");
+        assert_eq!(state.titanium_value(0), TITANIUM_VALUE + 1);
+        assert!(check_payment(&asteroid, &pay(2, 0, 3), 0, &state).is_ok());
+        put_in_play(&mut state, &cards, 0, "Advanced Alloys");
+        assert_eq!(state.steel_value(0), STEEL_VALUE + 1);
+        assert_eq!(state.titanium_value(0), TITANIUM_VALUE + 2);
+        assert!(check_payment(&asteroid, &pay(0, 0, 3), 0, &state).is_ok());
+        assert!(check_payment(&find_card(&cards, "Rover Construction"), &pay(2, 2, 0), 0, &state).is_ok());
+    }
+
+    #[test]
+    fn an_undone_card_refunds_the_payment() {
+        let (mut state, cards) = basic_game(2);
+        state.players[0].inventory.megacredits = 10;
+        state.players[0].inventory.steel = 10;
+        let card_id = give_card(&mut state, &cards, 0, "Rover Construction");
+        let mut command = PlayCard{
+            owner_id: 0,
+            card_id,
+            target_id: None,
+            target_card: None,
+            choice: None,
+            payment: Some(pay(2, 3, 0)),
+            colony: None,
+            paid: None,
+        };
+        command.apply(&mut state).unwrap();
+        assert_eq!((state.players[0].inventory.megacredits, state.players[0].inventory.steel), (8, 7));
+        command.undo(&mut state).unwrap();
+        assert_eq!((state.players[0].inventory.megacredits, state.players[0].inventory.steel), (10, 10));
+        assert_eq!(state.players[0].hand.len(), 1);
+    }
+
+    #[test]
+    fn cards_hold_only_their_own_resource() {
+        let (mut state, cards) = basic_game(2);
+        let birds = put_in_play(&mut state, &cards, 0, "Birds");
+        let mut command = ModCardResources{card_id: birds.to_owned(), resource: HoldableResource::Animals, count: 2};
+        command.apply(&mut state).unwrap();
+        assert_eq!(state.cards_in_play[0].resources, 2);
+        assert!(ModCardResources{card_id: birds.to_owned(), resource: HoldableResource::Microbes, count: 1}.apply(&mut state).is_err());
+        assert!(ModCardResources{card_id: birds, resource: HoldableResource::Animals, count: -3}.apply(&mut state).is_err());
+        assert_eq!(state.cards_in_play[0].resources, 2);
+        command.undo(&mut state).unwrap();
+        assert_eq!(state.cards_in_play[0].resources, 0);
+    }
+
+    fn setup_hand(state: &mut GameState, cards: &[Card], names: &[&str]) -> Vec<String> {
+        state.phase = Phase::Setup;
+        names.iter().map(|name| give_card(state, cards, 0, name)).collect()
+    }
+
+    #[test]
+    fn the_chosen_corporation_keeps_preludes_and_projects_in_the_hand() {
+        let (mut state, cards) = basic_game(2);
+        let ids = setup_hand(&mut state, &cards, &["Inventrix", "Tharsis Republic", "Allied Bank", "Pets"]);
+        let discarded = state.corporation_pile.discard_pile.len();
+        let mut command = ChooseCorporation{player_id: 0, card_id: ids[2].to_owned(), rejected: 0};
+        assert!(command.apply(&mut state).is_err());
+        let mut command = ChooseCorporation{player_id: 0, card_id: ids[1].to_owned(), rejected: 0};
+        command.apply(&mut state).unwrap();
+        assert_eq!(state.players[0].corporation.as_ref().unwrap().name, "Tharsis Republic");
+        assert!(state.players[0].pending_first_action);
+        assert_eq!(state.players[0].hand.iter().map(|c| c.id.to_owned()).collect::<Vec<String>>(), ids[2..].to_vec());
+        assert_eq!(state.corporation_pile.discard_pile.len(), discarded + 1);
+        command.undo(&mut state).unwrap();
+        assert!(state.players[0].corporation.is_none());
+        assert!(!state.players[0].pending_first_action);
+        assert_eq!(state.players[0].hand.len(), 4);
+        assert_eq!(state.corporation_pile.discard_pile.len(), discarded);
+    }
+
+    #[test]
+    fn exactly_two_preludes_are_kept() {
+        let (mut state, cards) = basic_game(2);
+        let ids = setup_hand(&mut state, &cards, &["Allied Bank", "Biofuels", "Biolabs", "Aquifer Turbines", "Pets"]);
+        let discarded = state.prelude_pile.discard_pile.len();
+        for card_ids in [vec![ids[0].to_owned()], ids[..3].to_vec(), vec![ids[0].to_owned(), ids[4].to_owned()]] {
+            assert!(ChoosePreludes{player_id: 0, card_ids, rejected: 0}.apply(&mut state).is_err());
+        }
+        let mut command = ChoosePreludes{player_id: 0, card_ids: ids[1..3].to_vec(), rejected: 0};
+        command.apply(&mut state).unwrap();
+        assert_eq!(state.players[0].preludes.iter().map(|c| c.id.to_owned()).collect::<Vec<String>>(), ids[1..3].to_vec());
+        assert_eq!(state.players[0].hand.len(), 1);
+        assert_eq!(state.prelude_pile.discard_pile.len(), discarded + 2);
+        command.undo(&mut state).unwrap();
+        assert!(state.players[0].preludes.is_empty());
+        assert_eq!(state.players[0].hand.len(), 5);
+        assert_eq!(state.prelude_pile.discard_pile.len(), discarded);
+    }
+
+    #[test]
+    fn corporations_and_preludes_are_chosen_in_the_setup_phase_only() {
+        let (mut state, cards) = basic_game(2);
+        let ids = setup_hand(&mut state, &cards, &["Inventrix", "Allied Bank", "Biofuels"]);
+        state.phase = Phase::Action;
+        assert!(ChooseCorporation{player_id: 0, card_id: ids[0].to_owned(), rejected: 0}.apply(&mut state).is_err());
+        assert!(ChoosePreludes{player_id: 0, card_ids: ids[1..].to_vec(), rejected: 0}.apply(&mut state).is_err());
+    }
+
+    fn draft_hands(state: &GameState) -> Vec<Vec<String>> {
+        state.players.iter().map(|p| p.draft_hand.iter().map(|c| c.id.to_owned()).collect()).collect()
+    }
+
+    #[test]
+    fn draft_hands_are_passed_in_both_directions() {
+        let (mut state, _) = basic_game(3);
+        for id in 0..3 {
+            DealDraft{player_id: id, count: 2}.apply(&mut state).unwrap();
+        }
+        let dealt = draft_hands(&state);
+        let mut forward = PassDraft{forward: true};
+        forward.apply(&mut state).unwrap();
+        assert_eq!(draft_hands(&state), vec![dealt[2].to_owned(), dealt[0].to_owned(), dealt[1].to_owned()]);
+        forward.undo(&mut state).unwrap();
+        assert_eq!(draft_hands(&state), dealt);
+        PassDraft{forward: false}.apply(&mut state).unwrap();
+        assert_eq!(draft_hands(&state), vec![dealt[1].to_owned(), dealt[2].to_owned(), dealt[0].to_owned()]);
+    }
+
+    #[test]
+    fn the_last_card_of_a_draft_is_kept() {
+        let (mut state, _) = basic_game(2);
+        for id in 0..2 {
+            DealDraft{player_id: id, count: 1}.apply(&mut state).unwrap();
+        }
+        let dealt = draft_hands(&state);
+        let mut pass = PassDraft{forward: true};
+        pass.apply(&mut state).unwrap();
+        assert!(state.players.iter().all(|p| p.draft_hand.is_empty()));
+        assert_eq!(state.players[0].research_queue[0].id, dealt[1][0]);
+        pass.undo(&mut state).unwrap();
+        assert_eq!(draft_hands(&state), dealt);
+        assert!(state.players.iter().all(|p| p.research_queue.is_empty()));
+    }
+
+
+    #[test]
+    fn corporations_with_an_unsupported_first_action_cannot_be_chosen() {
+        let (mut state, cards) = action_game(2, &[Deck::Basic, Deck::Venus]);
+        let ids = setup_hand(&mut state, &cards, &["Celestic", "Inventrix"]);
+        assert!(ChooseCorporation{player_id: 0, card_id: ids[0].to_owned(), rejected: 0}.apply(&mut state).is_err());
+        ChooseCorporation{player_id: 0, card_id: ids[1].to_owned(), rejected: 0}.apply(&mut state).unwrap();
     }
 }
-impl Error for CannotExecute {}
@@ -0,0 +1,648 @@
+use serde::{Deserialize, Serialize};
+
+use crate::card::{Card, Tags, Resource, NumberOrRef, TileType, HoldableResource};
+
+// One-time effects of a card, executed in order when the card is played
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Effect {
+    // removing resources from other players is optional and removes as much as they have
+    GainResource { resource: Resource, target: Target, multiplier: Multiplier },
+    // production decreases of other players are mandatory
+    ModProduction { resource: Resource, target: Target, multiplier: Multiplier },
+    ModRating { steps: i32, multiplier: Multiplier },
+    // removes up to the amount from the target and gives the removed resources to the player
+    Steal { resource: Resource, target: Target },
+    RaiseParameter { parameter: GlobalParameter, steps: u32 },
+    PlaceTile(TilePlacement),
+    // places a colony on the colony tile the player picked,
+    // some cards allow a second colony of the player on the same tile
+    PlaceColony { duplicate: bool },
+    DrawCards(u32),
+    // pays for a card action, building cards also accept steel and space cards titanium
+    Pay(u32),
+    // adds or removes animals, microbes, science or floaters on a card
+    CardResource { resource: HoldableResource, count: i32, target: CardTarget },
+    // the player picks exactly one of the options
+    Choice(Vec<Vec<Effect>>),
+    Conditional { condition: Condition, then: Vec<Effect>, otherwise: Vec<Effect> },
+}
+
+// victory points of a card at the end of the game
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum VictoryPoints {
+    Fixed(i32),
+    // `points` for every `per` resources on the card, e.g. 1 VP per 2 animals
+    PerResource { points: i32, per: u32 },
+    // `points` if the card holds at least `min` resources
+    MinResources { points: i32, min: u32 },
+    PerTag { tag: Tags, per: u32 },
+    // counts all tiles in play
+    PerTile { tile_type: TileType, per: u32 },
+    // oceans adjacent to the player's capital
+    PerAdjacentOcean,
+}
+
+// ongoing effects that change the cost or the requirements of the owner's cards
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Modifier {
+    // pay less for cards with the tag, or for every card if there is no tag
+    Discount { tag: Option<Tags>, amount: u32 },
+    // temperature, oxygen and ocean requirements are relaxed by this many steps
+    RequirementSteps(u32),
+    // like RequirementSteps, but only for the next card the player plays
+    NextRequirementSteps(u32),
+    // venus requirements are relaxed by this many steps
+    VenusRequirementSteps(u32),
+    // additional trade fleets
+    TradeFleets(u32),
+    // trading costs this many resources less
+    TradeDiscount(u32),
+    // the colony track is raised by this many steps before trading
+    TradeOffset(u32),
+}
+
+impl Modifier {
+    pub fn card_modifiers(card_name: &str) -> Option<Vec<Modifier>> {
+        let discount = |tag: Option<Tags>, amount| vec![Modifier::Discount{tag, amount}];
+        let modifiers = match card_name {
+            "Earth Office" | "Teractor" => discount(Some(Tags::Earth), 3),
+            "Space Station" | "Mass Converter" | "Quantum Extractor" | "Shuttles" => discount(Some(Tags::Space), 2),
+            "Warp Drive" => discount(Some(Tags::Space), 4),
+            "Cheung Shing Mars" => discount(Some(Tags::Building), 2),
+            "Valley Trust" => discount(Some(Tags::Science), 2),
+            // TODO also discount the power plant standard project
+            "ThorGate" => discount(Some(Tags::Energy), 3),
+            "Venus Waystation" => discount(Some(Tags::Venus), 2),
+            "Anti-Gravity Technology" | "Earth Catapult" => discount(None, 2),
+            "Research Outpost" => discount(None, 1),
+            "Sky Docks" => vec![Modifier::Discount{tag: None, amount: 1}, Modifier::TradeFleets(1)],
+            "Space Port" | "Space Port Colony" => vec![Modifier::TradeFleets(1)],
+            "Cryo-Sleep" | "Rim Freighters" => vec![Modifier::TradeDiscount(1)],
+            "Trade Envoys" | "Trading Colony" => vec![Modifier::TradeOffset(1)],
+            "Adaptation Technology" | "Inventrix" => vec![Modifier::RequirementSteps(2)],
+            "Special Design" => vec![Modifier::NextRequirementSteps(2)],
+            "Morning Star Inc." => vec![Modifier::VenusRequirementSteps(2)],
+            _ => return None,
+        };
+        Some(modifiers)
+    }
+}
+
+impl VictoryPoints {
+    pub fn from_card(card_name: &str, vp: &NumberOrRef) -> VictoryPoints {
+        let per_resource = |points, per| VictoryPoints::PerResource{points, per};
+        match card_name {
+            "Capital" => VictoryPoints::PerAdjacentOcean,
+            "Water Import From Europa" | "Ganymede Colony" | "Io Mining Industries" => VictoryPoints::PerTag{tag: Tags::Jovian, per: 1},
+            "Immigration Shuttles" => VictoryPoints::PerTile{tile_type: TileType::City, per: 3},
+            "Search For Life" => VictoryPoints::MinResources{points: 3, min: 1},
+            "Physics Complex" => per_resource(2, 1),
+            "Predators" | "Fish" | "Birds" | "Livestock" | "Penguins" | "Venusian Animals" |
+            "Stratospheric Birds" | "Security Fleet" | "Refugee Camps" => per_resource(1, 1),
+            "Ants" | "Small Animals" | "Ecological Zone" | "Herbivores" | "Pets" | "Floating Habs" |
+            "Jovian Lanterns" | "Sub-Zero Salt Fish" | "Venusian Insects" => per_resource(1, 2),
+            "Decomposers" | "Extremophiles" | "Stratopolis" => per_resource(1, 3),
+            "Tardigrades" => per_resource(1, 4),
+            _ => match vp {
+                NumberOrRef::Number(points) => VictoryPoints::Fixed(*points),
+                NumberOrRef::Ref => VictoryPoints::Fixed(0),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TilePlacement {
+    pub tile_type: TileType,
+    pub placement: Placement,
+}
+
+// where a tile may be placed, cards may disregard the normal placement restrictions
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Placement {
+    Normal,
+    OceanArea,
+    NonOceanArea,
+    // the area reserved for this tile, e.g. Noctis City
+    Reserved,
+    // next to no other tile
+    Isolated,
+    // one of the named special tiles that are not on the board
+    OffMars(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Target {
+    Own,
+    // the chosen player, this may also be the acting player
+    AnyPlayer,
+    // the chosen player, who must not be the acting player
+    Opponent,
+    // every other player at once
+    AllOpponents,
+    // the player whose event fired a trigger, e.g. the player of the microbe tag for Splice
+    EventPlayer,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CardTarget {
+    // the card that is played or whose action is used
+    This,
+    // any card in play that holds the resource, including opponents' cards
+    Any,
+    // like Any, but not this card
+    Other,
+    // like Any, but only cards with the tag
+    Tagged(Tags),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Scope {
+    Own,
+    Opponents,
+    All,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Multiplier {
+    Once,
+    // one step for every `per` tags, the played card's tags are included
+    PerTag { tag: Tags, per: u32, scope: Scope },
+    PerTile(TileType),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Condition {
+    HasTags { tag: Tags, count: u32 },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GlobalParameter {
+    Temperature,
+    Oxygen,
+    // raised by placing ocean tiles
+    Ocean,
+    Venus,
+}
+
+impl Effect {
+    pub(crate) fn gain(resource: Resource) -> Effect {
+        Effect::GainResource{resource, target: Target::Own, multiplier: Multiplier::Once}
+    }
+
+    fn remove_any(resource: Resource) -> Effect {
+        Effect::GainResource{resource, target: Target::AnyPlayer, multiplier: Multiplier::Once}
+    }
+
+    pub(crate) fn on_card(resource: HoldableResource, count: i32, target: CardTarget) -> Effect {
+        Effect::CardResource{resource, count, target}
+    }
+
+    pub(crate) fn production(resource: Resource) -> Effect {
+        Effect::ModProduction{resource, target: Target::Own, multiplier: Multiplier::Once}
+    }
+
+    fn production_any(resource: Resource) -> Effect {
+        Effect::ModProduction{resource, target: Target::AnyPlayer, multiplier: Multiplier::Once}
+    }
+
+    fn production_per(resource: Resource, multiplier: Multiplier) -> Effect {
+        Effect::ModProduction{resource, target: Target::Own, multiplier}
+    }
+
+    pub(crate) fn raise(parameter: GlobalParameter, steps: u32) -> Effect {
+        Effect::RaiseParameter{parameter, steps}
+    }
+
+    fn tile(tile_type: TileType) -> Effect {
+        Effect::tile_at(tile_type, Placement::Normal)
+    }
+
+    fn tile_at(tile_type: TileType, placement: Placement) -> Effect {
+        Effect::PlaceTile(TilePlacement{tile_type, placement})
+    }
+
+    fn colony() -> Effect {
+        Effect::PlaceColony{duplicate: false}
+    }
+
+    fn per_tag(tag: Tags, per: u32) -> Multiplier {
+        Multiplier::PerTag{tag, per, scope: Scope::Own}
+    }
+
+    // effects that can be read off the numeric columns of a card
+    pub fn from_card(card: &Card) -> Vec<Effect> {
+        let mut effects = Vec::new();
+        for res in card.resources.iter().filter(|r| !r.is_empty()) {
+            effects.push(Effect::gain(res.to_owned()));
+        }
+        for res in card.production.iter().filter(|r| !r.is_empty()) {
+            effects.push(Effect::production(res.to_owned()));
+        }
+        if let NumberOrRef::Number(steps) = card.terraforming_effect.temperature {
+            if steps > 0 {
+                effects.push(Effect::raise(GlobalParameter::Temperature, steps as u32));
+            }
+        }
+        if let NumberOrRef::Number(steps) = card.terraforming_effect.oxygen {
+            if steps > 0 {
+                effects.push(Effect::raise(GlobalParameter::Oxygen, steps as u32));
+            }
+        }
+        if let NumberOrRef::Number(steps) = card.terraforming_effect.venus {
+            if steps > 0 {
+                effects.push(Effect::raise(GlobalParameter::Venus, steps as u32));
+            }
+        }
+        if let NumberOrRef::Number(count) = card.terraforming_effect.ocean {
+            for _ in 0..count {
+                effects.push(Effect::tile(TileType::Ocean));
+            }
+        }
+        if let NumberOrRef::Number(steps) = card.terraforming_effect.tr {
+            if steps != 0 {
+                effects.push(Effect::ModRating{steps, multiplier: Multiplier::Once});
+            }
+        }
+        effects
+    }
+
+    // Cards marked with "Ref" in card_list.csv describe (parts of) their effect only in the card text.
+    // These are written out by hand and replace the effects derived from the numeric columns.
+    pub fn special_effects(card_name: &str) -> Option<Vec<Effect>> {
+        use GlobalParameter::*;
+        let effects = match card_name {
+            "Asteroid Mining Consortium" | "Great Escarpment Consortium" | "Power Supply Consortium" | "Energy Tapping" => {
+                let resource = match card_name {
+                    "Asteroid Mining Consortium" => Resource::Titanium(1),
+                    "Great Escarpment Consortium" => Resource::Steel(1),
+                    _ => Resource::Energy(1),
+                };
+                vec![Effect::production_any(resource.times(-1)), Effect::production(resource)]
+            }
+            "Cloud Seeding" => vec![
+                Effect::production(Resource::MegaCredits(-1)),
+                Effect::production_any(Resource::Heat(-1)),
+                Effect::production(Resource::Plants(2)),
+            ],
+            "Capital" => vec![
+                Effect::production(Resource::Energy(-2)),
+                Effect::production(Resource::MegaCredits(5)),
+                Effect::tile(TileType::Capital),
+            ],
+            "Asteroid" => vec![
+                Effect::raise(Temperature, 1),
+                Effect::gain(Resource::Titanium(2)),
+                Effect::remove_any(Resource::Plants(-3)),
+            ],
+            "Comet" => vec![
+                Effect::raise(Temperature, 1),
+                Effect::tile(TileType::Ocean),
+                Effect::remove_any(Resource::Plants(-3)),
+            ],
+            "Big Asteroid" => vec![
+                Effect::raise(Temperature, 2),
+                Effect::gain(Resource::Titanium(4)),
+                Effect::remove_any(Resource::Plants(-4)),
+            ],
+            "Deimos Down" => vec![
+                Effect::raise(Temperature, 3),
+                Effect::gain(Resource::Steel(4)),
+                Effect::remove_any(Resource::Plants(-8)),
+            ],
+            "Giant Ice Asteroid" => vec![
+                Effect::raise(Temperature, 2),
+                Effect::tile(TileType::Ocean),
+                Effect::tile(TileType::Ocean),
+                Effect::remove_any(Resource::Plants(-6)),
+            ],
+            "Mining Expedition" => vec![
+                Effect::raise(Oxygen, 1),
+                Effect::remove_any(Resource::Plants(-2)),
+                Effect::gain(Resource::Steel(2)),
+            ],
+            "Domed Crater" => vec![
+                Effect::production(Resource::Energy(-1)),
+                Effect::production(Resource::MegaCredits(3)),
+                Effect::gain(Resource::Plants(3)),
+                Effect::tile(TileType::City),
+            ],
+            "Open City" => vec![
+                Effect::production(Resource::Energy(-1)),
+                Effect::production(Resource::MegaCredits(4)),
+                Effect::gain(Resource::Plants(2)),
+                Effect::tile(TileType::City),
+            ],
+            "Noctis City" => vec![
+                Effect::production(Resource::Energy(-1)),
+                Effect::production(Resource::MegaCredits(3)),
+                Effect::tile_at(TileType::City, Placement::Reserved),
+            ],
+            "Cupola City" | "Corporate Stronghold" => vec![
+                Effect::production(Resource::Energy(-1)),
+                Effect::production(Resource::MegaCredits(3)),
+                Effect::tile(TileType::City),
+            ],
+            "Urbanized Area" => vec![
+                Effect::production(Resource::Energy(-1)),
+                Effect::production(Resource::MegaCredits(2)),
+                Effect::tile(TileType::City),
+            ],
+            "Immigrant City" => vec![
+                Effect::production(Resource::Energy(-1)),
+                Effect::production(Resource::MegaCredits(-2)),
+                Effect::tile(TileType::City),
+            ],
+            "Underground City" => vec![
+                Effect::production(Resource::Energy(-2)),
+                Effect::production(Resource::Steel(2)),
+                Effect::tile(TileType::City),
+            ],
+            "Phobos Space Haven" => vec![
+                Effect::production(Resource::Titanium(1)),
+                Effect::tile_at(TileType::City, Placement::OffMars(card_name.to_owned())),
+            ],
+            "Ganymede Colony" => vec![Effect::tile_at(TileType::City, Placement::OffMars(card_name.to_owned()))],
+            "Interplanetary Colony Ship" | "Trading Colony" => vec![Effect::colony()],
+            "Ice Moon Colony" => vec![Effect::colony(), Effect::tile(TileType::Ocean)],
+            "Mining Colony" => vec![Effect::production(Resource::Titanium(1)), Effect::colony()],
+            // TODO Pioneer Settlement requires that the player has no more than 1 colony
+            "Minority Refuge" | "Pioneer Settlement" => vec![Effect::production(Resource::MegaCredits(-2)), Effect::colony()],
+            "Research Colony" => vec![Effect::PlaceColony{duplicate: true}, Effect::DrawCards(2)],
+            "Space Port Colony" => vec![Effect::PlaceColony{duplicate: true}],
+            "Research Outpost" => vec![Effect::tile_at(TileType::City, Placement::Isolated)],
+            "Commercial District" => vec![
+                Effect::production(Resource::Energy(-1)),
+                Effect::production(Resource::MegaCredits(4)),
+                Effect::tile(TileType::Special),
+            ],
+            "Natural Preserve" => vec![
+                Effect::production(Resource::MegaCredits(1)),
+                Effect::tile_at(TileType::Special, Placement::Isolated),
+            ],
+            "Mohole Area" => vec![
+                Effect::production(Resource::Heat(4)),
+                Effect::tile_at(TileType::Special, Placement::OceanArea),
+            ],
+            "Nuclear Zone" | "Lava Flows" => vec![
+                Effect::tile(TileType::Special),
+                Effect::raise(Temperature, 2),
+            ],
+            "Mining Area" | "Mining Rights" | "Industrial Center" | "Ecological Zone" | "Restricted Area" => vec![
+                Effect::tile(TileType::Special),
+            ],
+            // placing a greenery raises the oxygen on its own
+            "Mangrove" => vec![Effect::tile_at(TileType::Greenery, Placement::OceanArea)],
+            "Plantation" => vec![Effect::tile(TileType::Greenery)],
+            "Protected Valley" => vec![
+                Effect::production(Resource::MegaCredits(2)),
+                Effect::tile_at(TileType::Greenery, Placement::OceanArea),
+            ],
+            "Artificial Lake" => vec![Effect::tile_at(TileType::Ocean, Placement::NonOceanArea)],
+            "Arctic Algae" => vec![Effect::gain(Resource::Plants(1))],
+            "Eos Chasma National Park" => vec![
+                Effect::gain(Resource::Plants(3)),
+                Effect::production(Resource::MegaCredits(2)),
+            ],
+            "Nitrogen-Rich Asteroid" => vec![
+                Effect::ModRating{steps: 2, multiplier: Multiplier::Once},
+                Effect::raise(Temperature, 1),
+                Effect::Conditional{
+                    condition: Condition::HasTags{tag: Tags::Plant, count: 3},
+                    then: vec![Effect::production(Resource::Plants(4))],
+                    otherwise: vec![Effect::production(Resource::Plants(1))],
+                },
+            ],
+            "Virus" => vec![Effect::remove_any(Resource::Plants(-5))],
+            "Fish" | "Small Animals" | "Herbivores" => vec![Effect::production_any(Resource::Plants(-1))],
+            "Birds" => vec![Effect::production_any(Resource::Plants(-2))],
+            "Biomass Combustors" => vec![
+                Effect::production_any(Resource::Plants(-1)),
+                Effect::production(Resource::Energy(2)),
+            ],
+            "Heat Trappers" => vec![
+                Effect::production_any(Resource::Heat(-2)),
+                Effect::production(Resource::Energy(1)),
+            ],
+            "Hackers" => vec![
+                Effect::production(Resource::Energy(-1)),
+                Effect::production_any(Resource::MegaCredits(-2)),
+                Effect::production(Resource::MegaCredits(2)),
+            ],
+            "Miranda Resort" | "Cartel" => vec![
+                Effect::production_per(Resource::MegaCredits(1), Effect::per_tag(Tags::Earth, 1)),
+            ],
+            "Satellites" => vec![
+                Effect::production_per(Resource::MegaCredits(1), Effect::per_tag(Tags::Space, 1)),
+            ],
+            "Toll Station" => vec![
+                Effect::production_per(Resource::MegaCredits(1), Multiplier::PerTag{tag: Tags::Space, per: 1, scope: Scope::Opponents}),
+            ],
+            "Medical Lab" => vec![
+                Effect::production_per(Resource::MegaCredits(1), Effect::per_tag(Tags::Building, 2)),
+            ],
+            "Power Grid" => vec![
+                Effect::production_per(Resource::Energy(1), Effect::per_tag(Tags::Energy, 1)),
+            ],
+            "Insects" => vec![
+                Effect::production_per(Resource::Plants(1), Effect::per_tag(Tags::Plant, 1)),
+            ],
+            "Worms" => vec![
+                Effect::production_per(Resource::Plants(1), Effect::per_tag(Tags::Microbe, 2)),
+            ],
+            "Zeppelins" => vec![
+                Effect::production_per(Resource::MegaCredits(1), Multiplier::PerTile(TileType::City)),
+            ],
+            "Energy Saving" => vec![
+                Effect::production_per(Resource::Energy(1), Multiplier::PerTile(TileType::City)),
+            ],
+            "Greenhouses" => vec![
+                Effect::GainResource{resource: Resource::Plants(1), target: Target::Own, multiplier: Multiplier::PerTile(TileType::City)},
+            ],
+            "Media Archives" => vec![
+                Effect::GainResource{
+                    resource: Resource::MegaCredits(1),
+                    target: Target::Own,
+                    multiplier: Multiplier::PerTag{tag: Tags::Event, per: 1, scope: Scope::All},
+                },
+            ],
+            "Terraforming Ganymede" => vec![
+                Effect::ModRating{steps: 1, multiplier: Effect::per_tag(Tags::Jovian, 1)},
+            ],
+            "Artificial Photosynthesis" => vec![
+                Effect::Choice(vec![
+                    vec![Effect::production(Resource::Plants(1))],
+                    vec![Effect::production(Resource::Energy(2))],
+                ]),
+            ],
+            "Sabotage" => vec![
+                Effect::Choice(vec![
+                    vec![Effect::remove_any(Resource::Titanium(-3))],
+                    vec![Effect::remove_any(Resource::Steel(-4))],
+                    vec![Effect::remove_any(Resource::MegaCredits(-7))],
+                ]),
+            ],
+            "Hired Raiders" => vec![
+                Effect::Choice(vec![
+                    vec![Effect::Steal{resource: Resource::Steel(2), target: Target::Opponent}],
+                    vec![Effect::Steal{resource: Resource::MegaCredits(3), target: Target::Opponent}],
+                ]),
+            ],
+            "Imported Hydrogen" => vec![
+                Effect::gain(Resource::Plants(3)),
+                Effect::tile(TileType::Ocean),
+            ],
+            "Large Convoy" => vec![
+                Effect::tile(TileType::Ocean),
+                Effect::DrawCards(2),
+                Effect::gain(Resource::Plants(5)),
+            ],
+            "Local Heat Trapping" => vec![
+                Effect::gain(Resource::Heat(-5)),
+                Effect::gain(Resource::Plants(4)),
+            ],
+            "Convoy From Europa" => vec![
+                Effect::tile(TileType::Ocean),
+                Effect::DrawCards(1),
+            ],
+            "Research" | "Technology Demonstration" => vec![Effect::DrawCards(2)],
+            "Lagrange Observatory" => vec![Effect::DrawCards(1)],
+            "Tharsis Republic" => vec![Effect::gain(Resource::MegaCredits(40))],
+            _ => return None,
+        };
+        Some(effects)
+    }
+    // The effects of a blue card's action, the costs are removed like any other resource.
+    pub fn card_action(card_name: &str) -> Option<Vec<Effect>> {
+        use GlobalParameter::*;
+        use HoldableResource::{Animals, Microbes, Science, Floaters};
+        let rating = Effect::ModRating{steps: 1, multiplier: Multiplier::Once};
+        // most cards either collect a resource or spend some of them for a bonus
+        let collect_or_spend = |resource: HoldableResource, spent: i32, bonus: Effect| vec![Effect::Choice(vec![
+            vec![Effect::on_card(resource.clone(), 1, CardTarget::This)],
+            vec![Effect::on_card(resource, -spent, CardTarget::This), bonus],
+        ])];
+        let oxygen_from_energy = |resource: Resource| vec![
+            Effect::gain(Resource::Energy(-4)),
+            Effect::gain(resource),
+            Effect::raise(Oxygen, 1),
+        ];
+        let action = match card_name {
+            "AI Central" => vec![Effect::DrawCards(2)],
+            "Birds" | "Fish" | "Livestock" | "Small Animals" | "Penguins" | "Stratospheric Birds" | "Sub-Zero Salt Fish" => {
+                vec![Effect::on_card(Animals, 1, CardTarget::This)]
+            }
+            "Tardigrades" | "Psychrophiles" | "Venusian Insects" => vec![Effect::on_card(Microbes, 1, CardTarget::This)],
+            "Predators" => vec![Effect::on_card(Animals, -1, CardTarget::Other), Effect::on_card(Animals, 1, CardTarget::This)],
+            "Ants" => vec![Effect::on_card(Microbes, -1, CardTarget::Other), Effect::on_card(Microbes, 1, CardTarget::This)],
+            "Extremophiles" => vec![Effect::on_card(Microbes, 1, CardTarget::Any)],
+            "Symbiotic Fungus" => vec![Effect::on_card(Microbes, 1, CardTarget::Other)],
+            "Extreme-Cold Fungus" => vec![Effect::Choice(vec![
+                vec![Effect::gain(Resource::Plants(1))],
+                vec![Effect::on_card(Microbes, 2, CardTarget::Other)],
+            ])],
+            "GHG Producing Bacteria" => collect_or_spend(Microbes, 2, Effect::raise(Temperature, 1)),
+            "Nitrite Reducing Bacteria" => collect_or_spend(Microbes, 3, rating),
+            "Regolith Eaters" => collect_or_spend(Microbes, 2, Effect::raise(Oxygen, 1)),
+            "Physics Complex" => vec![Effect::gain(Resource::Energy(-6)), Effect::on_card(Science, 1, CardTarget::This)],
+            "Aerial Mappers" => vec![Effect::Choice(vec![
+                vec![Effect::on_card(Floaters, 1, CardTarget::Any)],
+                vec![Effect::on_card(Floaters, -1, CardTarget::This), Effect::DrawCards(1)],
+            ])],
+            "Atmo Collectors" => vec![Effect::Choice(vec![
+                vec![Effect::on_card(Floaters, 1, CardTarget::This)],
+                vec![Effect::on_card(Floaters, -1, CardTarget::This), Effect::gain(Resource::Titanium(2))],
+                vec![Effect::on_card(Floaters, -1, CardTarget::This), Effect::gain(Resource::Energy(3))],
+                vec![Effect::on_card(Floaters, -1, CardTarget::This), Effect::gain(Resource::Heat(4))],
+            ])],
+            "Deuterium Export" => collect_or_spend(Floaters, 1, Effect::production(Resource::Energy(1))),
+            "Local Shading" => collect_or_spend(Floaters, 1, Effect::production(Resource::MegaCredits(1))),
+            "Red Spot Observatory" => collect_or_spend(Floaters, 1, Effect::DrawCards(1)),
+            "Dirigibles" => vec![Effect::on_card(Floaters, 1, CardTarget::Any)],
+            "Floater Technology" => vec![Effect::on_card(Floaters, 1, CardTarget::Other)],
+            "Floating Habs" => vec![Effect::gain(Resource::MegaCredits(-2)), Effect::on_card(Floaters, 1, CardTarget::Any)],
+            "Jovian Lanterns" => vec![Effect::gain(Resource::Titanium(-1)), Effect::on_card(Floaters, 2, CardTarget::This)],
+            "Extractor Balloons" => collect_or_spend(Floaters, 2, Effect::raise(Venus, 1)),
+            "Forced Precipitation" => vec![Effect::Choice(vec![
+                vec![Effect::gain(Resource::MegaCredits(-2)), Effect::on_card(Floaters, 1, CardTarget::This)],
+                vec![Effect::on_card(Floaters, -2, CardTarget::This), Effect::raise(Venus, 1)],
+            ])],
+            "Jet Stream Microscrappers" => vec![Effect::Choice(vec![
+                vec![Effect::gain(Resource::Titanium(-1)), Effect::on_card(Floaters, 2, CardTarget::This)],
+                vec![Effect::on_card(Floaters, -2, CardTarget::This), Effect::raise(Venus, 1)],
+            ])],
+            "Thermophiles" => vec![Effect::Choice(vec![
+                vec![Effect::on_card(Microbes, 1, CardTarget::Tagged(Tags::Venus))],
+                vec![Effect::on_card(Microbes, -2, CardTarget::This), Effect::raise(Venus, 1)],
+            ])],
+            "Venus Magnetizer" => vec![Effect::production(Resource::Energy(-1)), Effect::raise(Venus, 1)],
+            "Titan Air-Scrapping" => vec![Effect::Choice(vec![
+                vec![Effect::gain(Resource::Titanium(-1)), Effect::on_card(Floaters, 2, CardTarget::This)],
+                vec![Effect::on_card(Floaters, -2, CardTarget::This), rating],
+            ])],
+            "Aquifer Pumping" => vec![Effect::Pay(8), Effect::tile(TileType::Ocean)],
+            "Caretaker Contract" => vec![Effect::gain(Resource::Heat(-8)), rating],
+            "Development Center" => vec![Effect::gain(Resource::Energy(-1)), Effect::DrawCards(1)],
+            "Electro Catapult" => vec![Effect::Choice(vec![
+                vec![Effect::gain(Resource::Plants(-1)), Effect::gain(Resource::MegaCredits(7))],
+                vec![Effect::gain(Resource::Steel(-1)), Effect::gain(Resource::MegaCredits(7))],
+            ])],
+            "Equatorial Magnetizer" => vec![Effect::production(Resource::Energy(-1)), rating],
+            "Industrial Center" => vec![Effect::gain(Resource::MegaCredits(-7)), Effect::production(Resource::Steel(1))],
+            "Ironworks" => oxygen_from_energy(Resource::Steel(1)),
+            "Martian Rails" => vec![
+                Effect::gain(Resource::Energy(-1)),
+                Effect::GainResource{resource: Resource::MegaCredits(1), target: Target::Own, multiplier: Multiplier::PerTile(TileType::City)},
+            ],
+            "Ore Processor" => oxygen_from_energy(Resource::Titanium(1)),
+            "Restricted Area" => vec![Effect::gain(Resource::MegaCredits(-2)), Effect::DrawCards(1)],
+            "Space Elevator" => vec![Effect::gain(Resource::Steel(-1)), Effect::gain(Resource::MegaCredits(5))],
+            "Space Mirrors" => vec![Effect::gain(Resource::MegaCredits(-7)), Effect::production(Resource::Energy(1))],
+            "Steelworks" => oxygen_from_energy(Resource::Steel(2)),
+            "Underground Detonations" => vec![Effect::gain(Resource::MegaCredits(-10)), Effect::production(Resource::Heat(2))],
+            "Water Import From Europa" => vec![Effect::Pay(12), Effect::tile(TileType::Ocean)],
+            "Water Splitting Plant" => vec![Effect::gain(Resource::Energy(-3)), Effect::raise(Oxygen, 1)],
+            _ => return None,
+        };
+        Some(action)
+    }
+
+    // Some corporations have to take a special action as their first action in the game.
+    // Those of Vitor, Valley Trust, Celestic, Morning Star Inc. and Aridor are not supported yet.
+    pub fn first_action(card_name: &str) -> Option<Vec<Effect>> {
+        let action = match card_name {
+            "Tharsis Republic" => vec![Effect::tile(TileType::City)],
+            "Inventrix" => vec![Effect::DrawCards(3)],
+            "Poseidon" => vec![Effect::colony()],
+            _ => return None,
+        };
+        Some(action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn special_effects_replace_the_card_text() {
+        let expected = vec![
+            Effect::production(Resource::Energy(-2)),
+            Effect::production(Resource::MegaCredits(5)),
+            Effect::PlaceTile(TilePlacement{tile_type: TileType::Capital, placement: Placement::Normal}),
+        ];
+        assert_eq!(Effect::special_effects("Capital"), Some(expected));
+        assert_eq!(Effect::special_effects("Lichen"), None);
+    }
+
+    #[test]
+    fn other_players_are_targeted_by_the_special_effects() {
+        let expected = vec![
+            Effect::production_any(Resource::Energy(-1)),
+            Effect::production(Resource::Energy(1)),
+        ];
+        assert_eq!(Effect::special_effects("Energy Tapping"), Some(expected));
+        let asteroid = Effect::special_effects("Asteroid").unwrap();
+        assert_eq!(asteroid.last(), Some(&Effect::remove_any(Resource::Plants(-3))));
+    }
+}
@@ -0,0 +1,200 @@
+use serde::{Deserialize, Serialize};
+use std::mem::discriminant;
+
+use crate::card::{CardType, Tags, Resource, TileType, HoldableResource};
+use crate::effect::{Effect, Scope, CardTarget, GlobalParameter, Target, Multiplier};
+
+// Something that happens while the StateMachine builds the chain of a command.
+// The ongoing effects of cards in play and corporations react to it within the same chain.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    CardPlayed { player_id: usize, tags: Vec<Tags>, card_type: CardType, cost: u32 },
+    // tiles off mars are placed on special tiles, e.g. Phobos Space Haven
+    TilePlaced { player_id: usize, tile_type: TileType, on_mars: bool },
+    ParameterRaised { player_id: usize, parameter: GlobalParameter },
+    ProductionChanged { player_id: usize, resource: Resource },
+    ColonyPlaced { player_id: usize },
+    // selling patents is no paid project
+    StandardProject { player_id: usize, cost: u32 },
+    // the resources of the placement bonus on the map
+    PlacementBonus { player_id: usize, resources: Vec<Resource> },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum On {
+    // fires once for every matching tag
+    TagPlayed(Tags),
+    // event cards, optionally only those with the tag
+    EventPlayed(Option<Tags>),
+    // cards and standard projects with a basic cost of at least this much
+    CostAtLeast(u32),
+    // only cards with a basic cost of at least this much
+    CardCostAtLeast(u32),
+    StandardProject,
+    // fires once if the bonus contains any of these kinds of resources
+    PlacementBonus(Vec<Resource>),
+    TilePlaced(TileType),
+    // only tiles placed on the board, not those off mars
+    MarsTilePlaced(TileType),
+    ParameterRaised(GlobalParameter),
+    // fires once for every step the production of this kind of resource increases
+    ProductionRaised(Resource),
+    ColonyPlaced,
+}
+
+// An ongoing effect of a card. The effects always benefit the owner of the card.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Trigger {
+    pub on: On,
+    // whose events the trigger reacts to, seen from the owner of the card
+    pub scope: Scope,
+    pub effects: Vec<Effect>,
+}
+
+impl Event {
+    pub fn player_id(&self) -> usize {
+        match self {
+            Event::CardPlayed{player_id, ..} => *player_id,
+            Event::TilePlaced{player_id, ..} => *player_id,
+            Event::ParameterRaised{player_id, ..} => *player_id,
+            Event::ProductionChanged{player_id, ..} => *player_id,
+            Event::ColonyPlaced{player_id} => *player_id,
+            Event::StandardProject{player_id, ..} => *player_id,
+            Event::PlacementBonus{player_id, ..} => *player_id,
+        }
+    }
+}
+
+impl Trigger {
+    fn new(on: On, scope: Scope, effects: Vec<Effect>) -> Trigger {
+        Trigger{on, scope, effects}
+    }
+
+    // how often the trigger fires for the event
+    pub fn fires(&self, event: &Event, owner: usize) -> u32 {
+        let in_scope = match self.scope {
+            Scope::Own => event.player_id() == owner,
+            Scope::Opponents => event.player_id() != owner,
+            Scope::All => true,
+        };
+        if !in_scope {
+            return 0;
+        }
+        match (&self.on, event) {
+            (On::TagPlayed(tag), Event::CardPlayed{tags, ..}) => tags.iter().filter(|t| *t == tag).count() as u32,
+            (On::EventPlayed(tag), Event::CardPlayed{tags, card_type, ..}) if *card_type == CardType::Event => {
+                tag.as_ref().is_none_or(|tag| tags.contains(tag)) as u32
+            }
+            (On::CostAtLeast(min), Event::CardPlayed{cost, ..} | Event::StandardProject{cost, ..}) => (cost >= min) as u32,
+            (On::CardCostAtLeast(min), Event::CardPlayed{cost, ..}) => (cost >= min) as u32,
+            (On::StandardProject, Event::StandardProject{..}) => 1,
+            (On::PlacementBonus(expected), Event::PlacementBonus{resources, ..}) => {
+                resources.iter().any(|r| expected.iter().any(|e| discriminant(e) == discriminant(r))) as u32
+            }
+            // the capital is also a city
+            (On::TilePlaced(expected), Event::TilePlaced{tile_type, ..}) => {
+                (tile_type == expected || (*expected == TileType::City && *tile_type == TileType::Capital)) as u32
+            }
+            (On::MarsTilePlaced(expected), Event::TilePlaced{tile_type, on_mars, ..}) => {
+                (*on_mars && (tile_type == expected || (*expected == TileType::City && *tile_type == TileType::Capital))) as u32
+            }
+            (On::ParameterRaised(expected), Event::ParameterRaised{parameter, ..}) => (parameter == expected) as u32,
+            (On::ProductionRaised(expected), Event::ProductionChanged{resource, ..}) if discriminant(expected) == discriminant(resource) => {
+                resource.count().max(0) as u32
+            }
+            (On::ColonyPlaced, Event::ColonyPlaced{..}) => 1,
+            _ => 0,
+        }
+    }
+
+    // Ongoing effects that require a decision of the player, e.g. Mars University, are not supported yet.
+    // Discounts are no triggers, they are applied to the cost before a card is paid.
+    pub fn card_triggers(card_name: &str) -> Option<Vec<Trigger>> {
+        use HoldableResource::{Animals, Microbes};
+        let triggers = match card_name {
+            "Aphrodite" => vec![Trigger::new(On::ParameterRaised(GlobalParameter::Venus), Scope::All, vec![Effect::gain(Resource::MegaCredits(2))])],
+            "Poseidon" => vec![Trigger::new(On::ColonyPlaced, Scope::All, vec![Effect::production(Resource::MegaCredits(1))])],
+            "Saturn Systems" => vec![Trigger::new(On::TagPlayed(Tags::Jovian), Scope::All, vec![Effect::production(Resource::MegaCredits(1))])],
+            "Point Luna" => vec![Trigger::new(On::TagPlayed(Tags::Earth), Scope::Own, vec![Effect::DrawCards(1)])],
+            // the player of the microbe tag always takes the 2 MC instead of a microbe on the card
+            "Splice" => vec![Trigger::new(On::TagPlayed(Tags::Microbe), Scope::All, vec![
+                Effect::gain(Resource::MegaCredits(2)),
+                Effect::GainResource{resource: Resource::MegaCredits(2), target: Target::EventPlayer, multiplier: Multiplier::Once},
+            ])],
+            "Interplanetary Cinematics" => vec![Trigger::new(On::EventPlayed(None), Scope::Own, vec![Effect::gain(Resource::MegaCredits(2))])],
+            "Media Group" => vec![Trigger::new(On::EventPlayed(None), Scope::Own, vec![Effect::gain(Resource::MegaCredits(3))])],
+            "Optimal Aerobraking" => vec![Trigger::new(
+                On::EventPlayed(Some(Tags::Space)), Scope::Own,
+                vec![Effect::gain(Resource::MegaCredits(3)), Effect::gain(Resource::Heat(3))],
+            )],
+            "CrediCor" => vec![Trigger::new(On::CostAtLeast(20), Scope::Own, vec![Effect::gain(Resource::MegaCredits(4))])],
+            "Spin-Off Department" => vec![Trigger::new(On::CardCostAtLeast(20), Scope::Own, vec![Effect::DrawCards(1)])],
+            "Standard Technology" => vec![Trigger::new(On::StandardProject, Scope::Own, vec![Effect::gain(Resource::MegaCredits(3))])],
+            "Mining Guild" => vec![Trigger::new(
+                On::PlacementBonus(vec![Resource::Steel(1), Resource::Titanium(1)]), Scope::Own,
+                vec![Effect::production(Resource::Steel(1))],
+            )],
+            "Tharsis Republic" => vec![
+                Trigger::new(On::MarsTilePlaced(TileType::City), Scope::All, vec![Effect::production(Resource::MegaCredits(1))]),
+                Trigger::new(On::TilePlaced(TileType::City), Scope::Own, vec![Effect::gain(Resource::MegaCredits(3))]),
+            ],
+            "Immigrant City" => vec![Trigger::new(On::TilePlaced(TileType::City), Scope::All, vec![Effect::production(Resource::MegaCredits(1))])],
+            "Rover Construction" => vec![Trigger::new(On::TilePlaced(TileType::City), Scope::All, vec![Effect::gain(Resource::MegaCredits(2))])],
+            "Pets" => vec![Trigger::new(On::TilePlaced(TileType::City), Scope::All, vec![Effect::on_card(Animals, 1, CardTarget::This)])],
+            "Herbivores" => vec![Trigger::new(On::TilePlaced(TileType::Greenery), Scope::Own, vec![Effect::on_card(Animals, 1, CardTarget::This)])],
+            "Arctic Algae" => vec![Trigger::new(On::TilePlaced(TileType::Ocean), Scope::All, vec![Effect::gain(Resource::Plants(2))])],
+            "Decomposers" => [Tags::Animal, Tags::Plant, Tags::Microbe].iter()
+                .map(|tag| Trigger::new(On::TagPlayed(tag.to_owned()), Scope::Own, vec![Effect::on_card(Microbes, 1, CardTarget::This)]))
+                .collect(),
+            "Ecological Zone" => [Tags::Animal, Tags::Plant].iter()
+                .map(|tag| Trigger::new(On::TagPlayed(tag.to_owned()), Scope::Own, vec![Effect::on_card(Animals, 1, CardTarget::This)]))
+                .collect(),
+            "Venusian Animals" => vec![Trigger::new(On::TagPlayed(Tags::Science), Scope::Own, vec![Effect::on_card(Animals, 1, CardTarget::This)])],
+            "Manutech" => vec![
+                Resource::MegaCredits(1), Resource::Steel(1), Resource::Titanium(1),
+                Resource::Plants(1), Resource::Energy(1), Resource::Heat(1),
+            ].into_iter().map(|resource| Trigger::new(On::ProductionRaised(resource.to_owned()), Scope::Own, vec![Effect::gain(resource)])).collect(),
+            _ => return None,
+        };
+        Some(triggers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn city_placed(player_id: usize, on_mars: bool) -> Event {
+        Event::TilePlaced{player_id, tile_type: TileType::City, on_mars}
+    }
+
+    #[test]
+    fn tag_triggers_fire_once_per_tag_in_their_scope() {
+        let trigger = Trigger::new(On::TagPlayed(Tags::Science), Scope::Own, vec![]);
+        let event = Event::CardPlayed{player_id: 0, tags: vec![Tags::Science, Tags::Science, Tags::Earth], card_type: CardType::Active, cost: 11};
+        assert_eq!(trigger.fires(&event, 0), 2);
+        assert_eq!(trigger.fires(&event, 1), 0);
+        let trigger = Trigger::new(On::TagPlayed(Tags::Science), Scope::Opponents, vec![]);
+        assert_eq!(trigger.fires(&event, 1), 2);
+    }
+
+    #[test]
+    fn tharsis_republic_raises_the_production_for_cities_on_mars_only() {
+        let triggers = Trigger::card_triggers("Tharsis Republic").unwrap();
+        assert_eq!(triggers[0].fires(&city_placed(1, true), 0), 1);
+        assert_eq!(triggers[0].fires(&city_placed(1, false), 0), 0);
+        assert_eq!(triggers[0].fires(&Event::TilePlaced{player_id: 0, tile_type: TileType::Capital, on_mars: true}, 0), 1);
+        // the 3 MC are paid for all own cities
+        assert_eq!(triggers[1].fires(&city_placed(0, false), 0), 1);
+        assert_eq!(triggers[1].fires(&city_placed(1, true), 0), 0);
+    }
+
+    #[test]
+    fn production_triggers_fire_per_raised_step() {
+        let trigger = Trigger::new(On::ProductionRaised(Resource::Heat(1)), Scope::Own, vec![]);
+        let raised = |resource| Event::ProductionChanged{player_id: 0, resource};
+        assert_eq!(trigger.fires(&raised(Resource::Heat(2)), 0), 2);
+        assert_eq!(trigger.fires(&raised(Resource::Heat(-2)), 0), 0);
+        assert_eq!(trigger.fires(&raised(Resource::Energy(2)), 0), 0);
+    }
+}
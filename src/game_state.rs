@@ -1,11 +1,30 @@
 use serde::{Deserialize, Serialize};
 use rand::prelude::*;
 
-use crate::player::Player;
-use crate::card::{Card, Deck, CardType};
+use crate::player::{Player, STEEL_VALUE, TITANIUM_VALUE, BASIC_PRODUCTION, SOLO_TF_RATING};
+use crate::card::{Card, Deck, CardType, Tags, TileType, GlobalRequirements, HoldableResource};
+use crate::board::{self, Tile, SpecialTile};
+use crate::colony::{self, ColonyTile};
+use crate::score::FinalScore;
+use crate::effect::{Scope, Modifier, VictoryPoints};
 use crate::card_pile::CardPile;
 use crate::commands::CannotExecute;
 
+pub const MIN_TEMPERATURE: i32 = -30;
+pub const MAX_TEMPERATURE: i32 = 8;
+// one step raises the temperature by 2°C
+pub const TEMPERATURE_STEP: i32 = 2;
+pub const MAX_OXYGEN: u32 = 14;
+pub const MAX_OCEANS: u32 = 9;
+// one step raises venus by 2%
+pub const VENUS_STEP: u32 = 2;
+pub const MAX_VENUS: u32 = 30;
+// a solo game ends after 14 generations, or 12 with preludes
+pub const SOLO_GENERATIONS: u32 = 14;
+pub const SOLO_PRELUDE_GENERATIONS: u32 = 12;
+// corporations whose first action is not supported yet, they are neither dealt nor chosen
+pub const UNSUPPORTED_CORPORATIONS: [&str; 5] = ["Vitor", "Valley Trust", "Celestic", "Morning Star Inc.", "Aridor"];
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GameState {
     pub phase: Phase,
@@ -15,59 +34,68 @@ pub struct GameState {
     pub oxygen: u32,
     pub temperature: i32,
     pub oceans_placed: u32,
+    pub venus: u32,
     pub tiles: Vec<Tile>,
     pub special_tiles: Vec<SpecialTile>,
+    // empty unless the colonies expansion is used
+    pub colonies: Vec<ColonyTile>,
     pub milestones: Vec<Milestone>,
     pub awards: Vec<Award>,
     pub cards_in_play: Vec<OwnedCard>,
     pub players: Vec<Player>,
     pub project_pile: CardPile,
     pub corporation_pile: CardPile,
+    pub prelude_pile: CardPile,
+    // projects are drafted before they are bought
+    pub drafting: bool,
+    // the corporate era cards are used, players start without production
+    pub corporate_era: bool,
+    // the prelude expansion is used
+    pub use_preludes: bool,
+    // the venus next expansion is used
+    pub use_venus: bool,
+    // the colonies expansion is used
+    pub use_colonies: bool,
+    // a single player plays against the generation limit
+    pub solo: bool,
+    // filled in once the game is over
+    pub final_scores: Vec<FinalScore>,
+    // a solo game is won if the planet is terraformed at the end of the game
+    pub solo_won: Option<bool>,
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum Phase {
     Init, Setup, Research, Action, Production,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Tile {
-    pub x: u32,
-    pub z: u32,
-    pub tile_type: TileType,
-    pub name: String,
-    pub resources: Vec<Resources>,
-    pub reserved: TileType,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub enum TileType {
-    Empty,
-    City,
-    Greenery,
-    Ocean,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SpecialTile {
-    pub tile_type: TileType,
-    pub name: String,
-    pub resources: Vec<Resources>,
-    pub reserved: TileType,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub enum Resources {
-    Steel,
-    Titanium,
-    Card,
-    Plant,
+    // after the last production phase players may convert their plants into greenery one last time
+    FinalGreenery, End,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OwnedCard {
     pub card: Card,
     pub owner: usize,
+    // animals, microbes, science or other resources held by the card
+    pub resources: u32,
+    // blue card actions can be used once per generation
+    pub used: bool,
+}
+
+impl OwnedCard {
+    // victory points at the end of the game, some depend on the resources held by the card
+    pub fn victory_points(&self, game_state: &GameState) -> i32 {
+        match &self.card.victory_points {
+            VictoryPoints::Fixed(points) => *points,
+            VictoryPoints::PerResource{points, per} => points * (self.resources / per) as i32,
+            VictoryPoints::MinResources{points, min} => if self.resources >= *min { *points } else { 0 },
+            VictoryPoints::PerTag{tag, per} => (game_state.count_tags(self.owner, tag, &Scope::Own) / *per as usize) as i32,
+            VictoryPoints::PerTile{tile_type, per} => (game_state.count_tiles(tile_type) / *per as usize) as i32,
+            VictoryPoints::PerAdjacentOcean => game_state.tiles.iter()
+                .filter(|t| t.owner == Some(self.owner) && t.tile_type == TileType::Capital)
+                .map(|capital| board::neighbours(&game_state.tiles, capital).iter().filter(|n| n.tile_type == TileType::Ocean).count())
+                .sum::<usize>() as i32,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -76,13 +104,14 @@ pub struct Award {
     pub owner: Option<usize>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Awards {
     Landlord,
     Banker,
     Scientist,
     Thermalist,
     Miner,
+    Venuphile,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -91,20 +120,82 @@ pub struct Milestone {
     pub owner: Option<usize>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Milestones {
     Terraformer,
     Mayor,
     Gardener,
     Builder,
     Planner,
+    Hoverlord,
+}
+
+impl Awards {
+    // the value players compete for at the end of the game
+    pub fn metric(&self, game_state: &GameState, player_id: usize) -> i32 {
+        let player = &game_state.players[player_id];
+        match self {
+            Awards::Landlord => {
+                let on_mars = game_state.tiles.iter().filter(|t| t.owner == Some(player_id)).count();
+                let off_mars = game_state.special_tiles.iter().filter(|t| t.owner == Some(player_id)).count();
+                (on_mars + off_mars) as i32
+            }
+            Awards::Banker => player.production.megacredits,
+            Awards::Scientist => game_state.count_played_tags(player_id, &Tags::Science) as i32,
+            Awards::Thermalist => player.inventory.heat as i32,
+            Awards::Miner => (player.inventory.steel + player.inventory.titanium) as i32,
+            Awards::Venuphile => game_state.count_played_tags(player_id, &Tags::Venus) as i32,
+        }
+    }
+}
+
+impl Milestones {
+    pub fn progress(&self, game_state: &GameState, player_id: usize) -> i32 {
+        let player = &game_state.players[player_id];
+        let owned_tiles = |tile_type: TileType| game_state.tiles.iter()
+            .filter(|t| t.owner == Some(player_id) && t.tile_type == tile_type)
+            .count() as i32;
+        match self {
+            Milestones::Terraformer => player.tf_rating,
+            Milestones::Mayor => {
+                let off_mars = game_state.special_tiles.iter().filter(|t| t.owner == Some(player_id)).count() as i32;
+                owned_tiles(TileType::City) + owned_tiles(TileType::Capital) + off_mars
+            }
+            Milestones::Gardener => owned_tiles(TileType::Greenery),
+            Milestones::Builder => game_state.count_played_tags(player_id, &Tags::Building) as i32,
+            Milestones::Planner => player.hand.len() as i32,
+            Milestones::Hoverlord => game_state.cards_in_play.iter()
+                .filter(|c| c.owner == player_id && c.card.interactions.holds_resources == HoldableResource::Floaters)
+                .map(|c| c.resources as i32)
+                .sum(),
+        }
+    }
+
+    pub fn requirement(&self) -> i32 {
+        match self {
+            Milestones::Terraformer => 35,
+            Milestones::Mayor => 3,
+            Milestones::Gardener => 3,
+            Milestones::Builder => 8,
+            Milestones::Planner => 16,
+            Milestones::Hoverlord => 7,
+        }
+    }
 }
 
 impl GameState {
+    // the cards are passed to the next player in even generations and to the previous one in odd generations
+    pub fn draft_forward(&self) -> bool {
+        self.generation % 2 == 0
+    }
+
     pub fn add_player(&mut self) -> () {
-        self.players.push(
-            Player::new(self.players.len())
-        )
+        let start_production = if self.corporate_era { 0 } else { BASIC_PRODUCTION };
+        let mut player = Player::new(self.players.len(), start_production);
+        if self.solo {
+            player.tf_rating = SOLO_TF_RATING;
+        }
+        self.players.push(player)
     }
 
     pub fn get_player(&self, id: usize) -> Result<&Player, CannotExecute> {
@@ -121,10 +212,171 @@ impl GameState {
         }
     }
 
-    pub fn new(cards: &mut Vec<Card>, used_decks: &Vec<Deck>, player_count: usize) -> GameState {
+    // only solo games have a generation limit
+    pub fn generation_limit(&self) -> Option<u32> {
+        match (self.solo, self.use_preludes) {
+            (false, _) => None,
+            (true, false) => Some(SOLO_GENERATIONS),
+            (true, true) => Some(SOLO_PRELUDE_GENERATIONS),
+        }
+    }
+
+    // with venus next, venus has to be terraformed as well
+    pub fn is_terraformed(&self) -> bool {
+        self.temperature >= MAX_TEMPERATURE && self.oxygen >= MAX_OXYGEN && self.oceans_placed >= MAX_OCEANS
+            && (!self.use_venus || self.venus >= MAX_VENUS)
+    }
+
+    pub fn player_tags(&self, player_id: usize) -> Vec<&Tags> {
+        self.cards_in_play.iter().filter(|c| c.owner == player_id).map(|c| &c.card.tags).flatten().collect()
+    }
+
+    pub fn count_tags(&self, player_id: usize, tag: &Tags, scope: &Scope) -> usize {
+        self.cards_in_play.iter()
+            .filter(|c| match scope {
+                Scope::Own => c.owner == player_id,
+                Scope::Opponents => c.owner != player_id,
+                Scope::All => true,
+            })
+            .map(|c| &c.card.tags).flatten()
+            .filter(|t| *t == tag)
+            .count()
+    }
+
+    // tags on events do not count once the event is played
+    pub fn count_played_tags(&self, player_id: usize, tag: &Tags) -> usize {
+        self.cards_in_play.iter()
+            .filter(|c| c.owner == player_id && c.card.card_type != CardType::Event)
+            .map(|c| &c.card.tags).flatten()
+            .filter(|t| *t == tag)
+            .count()
+    }
+
+    fn owns_card(&self, player_id: usize, card_name: &str) -> bool {
+        self.cards_in_play.iter().any(|c| c.owner == player_id && c.card.name == card_name)
+    }
+
+    // Advanced Alloys makes steel and titanium worth 1 MC extra
+    pub fn steel_value(&self, player_id: usize) -> u32 {
+        STEEL_VALUE + self.owns_card(player_id, "Advanced Alloys") as u32
+    }
+
+    pub fn titanium_value(&self, player_id: usize) -> u32 {
+        let bonus = self.players.get(player_id).map_or(0, |p| p.titanium_bonus());
+        TITANIUM_VALUE + bonus + self.owns_card(player_id, "Advanced Alloys") as u32
+    }
+
+    fn player_modifiers(&self, player_id: usize) -> Vec<&Modifier> {
+        let corporation = self.players.get(player_id).and_then(|p| p.corporation.as_ref());
+        self.cards_in_play.iter()
+            .filter(|c| c.owner == player_id)
+            .map(|c| &c.card)
+            .chain(corporation)
+            .flat_map(|card| &card.modifiers)
+            .collect()
+    }
+
+    // the price the player has to pay for the card, every discount applies once per card
+    pub fn card_cost(&self, player_id: usize, card: &Card) -> u32 {
+        let discount: u32 = self.player_modifiers(player_id).iter()
+            .filter_map(|m| match m {
+                Modifier::Discount{tag: Some(tag), amount} if card.tags.contains(tag) => Some(amount),
+                Modifier::Discount{tag: None, amount} => Some(amount),
+                _ => None,
+            })
+            .sum();
+        card.cost.saturating_sub(discount)
+    }
+
+    // every player starts with one trade fleet
+    pub fn trade_fleets(&self, player_id: usize) -> u32 {
+        1 + self.player_modifiers(player_id).iter()
+            .filter_map(|m| match m {
+                Modifier::TradeFleets(count) => Some(count),
+                _ => None,
+            })
+            .sum::<u32>()
+    }
+
+    // trading costs this many resources less
+    pub fn trade_discount(&self, player_id: usize) -> u32 {
+        self.player_modifiers(player_id).iter()
+            .filter_map(|m| match m {
+                Modifier::TradeDiscount(amount) => Some(amount),
+                _ => None,
+            })
+            .sum()
+    }
+
+    // the colony track is raised by this many steps before trading
+    pub fn trade_offset(&self, player_id: usize) -> u32 {
+        self.player_modifiers(player_id).iter()
+            .filter_map(|m| match m {
+                Modifier::TradeOffset(steps) => Some(steps),
+                _ => None,
+            })
+            .sum()
+    }
+
+    pub fn venus_requirement_steps(&self, player_id: usize) -> u32 {
+        self.player_modifiers(player_id).iter()
+            .filter_map(|m| match m {
+                Modifier::VenusRequirementSteps(steps) => Some(steps),
+                _ => None,
+            })
+            .sum()
+    }
+
+    // Special Design only relaxes the requirements while it is the last card the player played
+    pub fn requirement_steps(&self, player_id: usize) -> u32 {
+        let steps: u32 = self.player_modifiers(player_id).iter()
+            .filter_map(|m| match m {
+                Modifier::RequirementSteps(steps) => Some(steps),
+                _ => None,
+            })
+            .sum();
+        let next_steps: u32 = self.cards_in_play.iter().rev()
+            .find(|c| c.owner == player_id)
+            .map_or(0, |c| c.card.modifiers.iter().filter_map(|m| match m {
+                Modifier::NextRequirementSteps(steps) => Some(steps),
+                _ => None,
+            }).sum());
+        steps + next_steps
+    }
+
+    // the global requirements of the card as they apply to the player
+    pub fn requirement_window(&self, player_id: usize, card: &Card) -> GlobalRequirements {
+        let steps = self.requirement_steps(player_id);
+        let venus_steps = self.venus_requirement_steps(player_id);
+        let global = &card.requirements.global;
+        GlobalRequirements {
+            min_temperature: global.min_temperature - (steps as i32 * TEMPERATURE_STEP),
+            max_temperature: global.max_temperature + (steps as i32 * TEMPERATURE_STEP),
+            min_oxygen: global.min_oxygen.saturating_sub(steps),
+            max_oxygen: global.max_oxygen + steps,
+            min_ocean: global.min_ocean.saturating_sub(steps),
+            max_ocean: global.max_ocean + steps,
+            min_venus: global.min_venus.saturating_sub(venus_steps * VENUS_STEP),
+            max_venus: global.max_venus + venus_steps * VENUS_STEP,
+        }
+    }
+
+    // counts the tiles on mars, the capital is also a city
+    pub fn count_tiles(&self, tile_type: &TileType) -> usize {
+        self.tiles.iter()
+            .filter(|t| t.tile_type == *tile_type || (*tile_type == TileType::City && t.tile_type == TileType::Capital))
+            .count()
+    }
+
+    pub fn new(cards: &mut Vec<Card>, used_decks: &Vec<Deck>, player_count: usize, drafting: bool) -> GameState {
         let deck: Vec<Card> = cards.iter().filter(|card| used_decks.contains(&card.deck)).cloned().collect();
-        let mut projects: Vec<Card> = deck.iter().filter(|card| card.card_type != CardType::Corporation).cloned().collect();
-        let mut corporations: Vec<Card> = deck.iter().filter(|card| card.card_type == CardType::Corporation).cloned().collect();
+        let mut projects: Vec<Card> = deck.iter()
+            .filter(|card| card.card_type != CardType::Corporation && card.card_type != CardType::Prelude)
+            .cloned().collect();
+        let mut corporations: Vec<Card> = deck.iter()
+            .filter(|card| card.card_type == CardType::Corporation && !UNSUPPORTED_CORPORATIONS.contains(&card.name.as_str()))
+            .cloned().collect();
+        let mut preludes: Vec<Card> = deck.iter().filter(|card| card.card_type == CardType::Prelude).cloned().collect();
         let start_player_id = rand::thread_rng().next_u32() as usize % player_count;
         let mut state = GameState {
             phase: Phase::Init,
@@ -132,75 +384,15 @@ impl GameState {
             start_player: start_player_id,
             active_player: start_player_id,
             oxygen: 0,
-            temperature: -30,
+            temperature: MIN_TEMPERATURE,
             oceans_placed: 0,
-            tiles: vec![
-                Tile {x: 0, z: 4, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Steel, Resources::Steel], reserved: TileType::Empty},
-                Tile {x: 0, z: 5, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Steel, Resources::Steel], reserved: TileType::Ocean},
-                Tile {x: 0, z: 6, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty},
-                Tile {x: 0, z: 7, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Card], reserved: TileType::Ocean},
-                Tile {x: 0, z: 8, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Ocean},
-                Tile {x: 1, z: 3, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty},
-                Tile {x: 1, z: 4, tile_type: TileType::Empty, name: "Tharsis Tholus".to_owned(), resources: vec![Resources::Steel], reserved: TileType::Empty},
-                Tile {x: 1, z: 5, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty},
-                Tile {x: 1, z: 6, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty},
-                Tile {x: 1, z: 7, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty},
-                Tile {x: 1, z: 8, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Card, Resources::Card], reserved: TileType::Ocean},
-                Tile {x: 2, z: 2, tile_type: TileType::Empty, name: "Ascraeus Mons".to_owned(), resources: vec![Resources::Card], reserved: TileType::Empty},
-                Tile {x: 2, z: 3, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty},
-                Tile {x: 2, z: 4, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty},
-                Tile {x: 2, z: 5, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty},
-                Tile {x: 2, z: 6, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty},
-                Tile {x: 2, z: 7, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty},
-                Tile {x: 2, z: 8, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Steel], reserved: TileType::Empty},
-                Tile {x: 3, z: 1, tile_type: TileType::Empty, name: "Pavonis Mons".to_owned(), resources: vec![Resources::Plant, Resources::Titanium], reserved: TileType::Empty},
-                Tile {x: 3, z: 2, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant], reserved: TileType::Empty},
-                Tile {x: 3, z: 3, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant], reserved: TileType::Empty},
-                Tile {x: 3, z: 4, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant], reserved: TileType::Empty},
-                Tile {x: 3, z: 5, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant, Resources::Plant], reserved: TileType::Empty},
-                Tile {x: 3, z: 6, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant], reserved: TileType::Empty},
-                Tile {x: 3, z: 7, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant], reserved: TileType::Empty},
-                Tile {x: 3, z: 8, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant, Resources::Plant], reserved: TileType::Ocean},
-                Tile {x: 4, z: 0, tile_type: TileType::Empty, name: "Arsia Mons".to_owned(), resources: vec![Resources::Plant, Resources::Plant], reserved: TileType::Empty},
-                Tile {x: 4, z: 1, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant, Resources::Plant], reserved: TileType::Empty},
-                Tile {x: 4, z: 2, tile_type: TileType::Empty, name: "Noctis City".to_owned(), resources: vec![Resources::Plant, Resources::Plant], reserved: TileType::City},
-                Tile {x: 4, z: 3, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant, Resources::Plant], reserved: TileType::Ocean},
-                Tile {x: 4, z: 4, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant, Resources::Plant], reserved: TileType::Ocean},
-                Tile {x: 4, z: 5, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant, Resources::Plant], reserved: TileType::Ocean},
-                Tile {x: 4, z: 6, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant, Resources::Plant], reserved: TileType::Empty},
-                Tile {x: 4, z: 7, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant, Resources::Plant], reserved: TileType::Empty},
-                Tile {x: 4, z: 8, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant, Resources::Plant], reserved: TileType::Empty},
-                Tile {x: 5, z: 0, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant], reserved: TileType::Empty},
-                Tile {x: 5, z: 1, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant, Resources::Plant], reserved: TileType::Empty},
-                Tile {x: 5, z: 2, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant], reserved: TileType::Empty},
-                Tile {x: 5, z: 3, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant], reserved: TileType::Empty},
-                Tile {x: 5, z: 4, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant], reserved: TileType::Empty},
-                Tile {x: 5, z: 5, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant], reserved: TileType::Ocean},
-                Tile {x: 5, z: 6, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant], reserved: TileType::Ocean},
-                Tile {x: 5, z: 7, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Plant], reserved: TileType::Ocean},
-                Tile {x: 6, z: 0, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty},
-                Tile {x: 6, z: 1, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty},
-                Tile {x: 6, z: 2, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty},
-                Tile {x: 6, z: 3, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty},
-                Tile {x: 6, z: 4, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty},
-                Tile {x: 6, z: 5, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Steel], reserved: TileType::Empty},
-                Tile {x: 6, z: 6, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty},
-                Tile {x: 7, z: 0, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Steel, Resources::Steel], reserved: TileType::Empty},
-                Tile {x: 7, z: 1, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty},
-                Tile {x: 7, z: 2, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Card], reserved: TileType::Empty},
-                Tile {x: 7, z: 3, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Card], reserved: TileType::Empty},
-                Tile {x: 7, z: 4, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty},
-                Tile {x: 7, z: 5, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Titanium], reserved: TileType::Empty},
-                Tile {x: 8, z: 0, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Steel], reserved: TileType::Empty},
-                Tile {x: 8, z: 1, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Steel, Resources::Steel], reserved: TileType::Empty},
-                Tile {x: 8, z: 2, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty},
-                Tile {x: 8, z: 3, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![], reserved: TileType::Empty},
-                Tile {x: 8, z: 4, tile_type: TileType::Empty, name: "".to_owned(), resources: vec![Resources::Titanium, Resources::Titanium], reserved: TileType::Ocean}
-            ],
-            special_tiles: vec![
-                SpecialTile {name: "Phobos Space Haven".to_owned(), tile_type: TileType::Empty, resources: vec![], reserved: TileType::City},
-                SpecialTile {name: "Ganymede Colony".to_owned(), tile_type: TileType::Empty, resources: vec![], reserved: TileType::City}
-            ],
+            venus: 0,
+            tiles: board::tharsis(),
+            special_tiles: board::tharsis_special(),
+            colonies: match used_decks.contains(&Deck::Colonies) {
+                true => colony::deal_colonies(player_count),
+                false => vec![],
+            },
             milestones: vec![
                 Milestone {name: Milestones::Terraformer, owner: None},
                 Milestone {name: Milestones::Mayor, owner: None},
@@ -219,10 +411,128 @@ impl GameState {
             players: vec![],
             project_pile: CardPile::new(projects.as_mut()),
             corporation_pile: CardPile::new(corporations.as_mut()),
+            prelude_pile: CardPile::new(preludes.as_mut()),
+            drafting: drafting,
+            corporate_era: used_decks.contains(&Deck::Corporate),
+            use_preludes: used_decks.contains(&Deck::Prelude),
+            use_venus: used_decks.contains(&Deck::Venus),
+            use_colonies: used_decks.contains(&Deck::Colonies),
+            solo: player_count == 1,
+            final_scores: vec![],
+            solo_won: None,
         };
+        if state.solo {
+            board::place_neutral_cities(&mut state.tiles);
+        }
+        if state.use_venus {
+            state.milestones.push(Milestone {name: Milestones::Hoverlord, owner: None});
+            state.awards.push(Award {name: Awards::Venuphile, owner: None});
+        }
         for _ in 0..player_count {
             state.add_player();
         }
         return state;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{action_game, basic_game, find_card, put_in_play};
+
+    #[test]
+    fn discounts_apply_to_cards_with_the_tag() {
+        let (mut game_state, cards) = basic_game(2);
+        let pets = find_card(&cards, "Pets");
+        let lichen = find_card(&cards, "Lichen");
+        put_in_play(&mut game_state, &cards, 0, "Earth Office");
+        assert_eq!(game_state.card_cost(0, &pets), 7);
+        assert_eq!(game_state.card_cost(0, &lichen), 7);
+        assert_eq!(game_state.card_cost(1, &pets), 10);
+    }
+
+    #[test]
+    fn discounts_without_a_tag_apply_to_every_card_and_add_up() {
+        let (mut game_state, cards) = basic_game(2);
+        put_in_play(&mut game_state, &cards, 0, "Earth Office");
+        put_in_play(&mut game_state, &cards, 0, "Earth Catapult");
+        assert_eq!(game_state.card_cost(0, &find_card(&cards, "Pets")), 5);
+        assert_eq!(game_state.card_cost(0, &find_card(&cards, "Lichen")), 5);
+        assert_eq!(game_state.card_cost(0, &find_card(&cards, "Sabotage")), 0);
+    }
+
+    #[test]
+    fn inventrix_relaxes_the_requirements_by_2_steps() {
+        let (mut game_state, cards) = basic_game(2);
+        let lichen = find_card(&cards, "Lichen");
+        let algae = find_card(&cards, "Arctic Algae");
+        assert_eq!(game_state.requirement_window(0, &lichen).min_temperature, -24);
+        game_state.players[0].corporation = Some(find_card(&cards, "Inventrix"));
+        assert_eq!(game_state.requirement_window(0, &lichen).min_temperature, -28);
+        assert_eq!(game_state.requirement_window(0, &algae).max_temperature, -8);
+        assert_eq!(game_state.requirement_window(1, &lichen).min_temperature, -24);
+    }
+
+    #[test]
+    fn special_design_relaxes_the_requirements_of_the_next_card_only() {
+        let (mut game_state, cards) = basic_game(2);
+        let lichen = find_card(&cards, "Lichen");
+        put_in_play(&mut game_state, &cards, 0, "Special Design");
+        assert_eq!(game_state.requirement_window(0, &lichen).min_temperature, -28);
+        game_state.players[0].corporation = Some(find_card(&cards, "Inventrix"));
+        assert_eq!(game_state.requirement_window(0, &lichen).min_temperature, -32);
+        // other players' cards do not end the effect
+        put_in_play(&mut game_state, &cards, 1, "Power Plant");
+        assert_eq!(game_state.requirement_window(0, &lichen).min_temperature, -32);
+        put_in_play(&mut game_state, &cards, 0, "Power Plant");
+        assert_eq!(game_state.requirement_window(0, &lichen).min_temperature, -28);
+    }
+
+    #[test]
+    fn morning_star_inc_relaxes_the_venus_requirements_by_2_steps() {
+        let (mut game_state, cards) = basic_game(2);
+        let mining = find_card(&cards, "Ishtar Mining");
+        assert_eq!(game_state.requirement_window(0, &mining).min_venus, 8);
+        game_state.players[0].corporation = Some(find_card(&cards, "Morning Star Inc."));
+        let window = game_state.requirement_window(0, &mining);
+        assert_eq!((window.min_venus, window.min_temperature), (4, -30));
+    }
+
+    #[test]
+    fn corporate_era_games_start_without_production() {
+        let (game_state, _) = action_game(2, &[Deck::Basic, Deck::Corporate]);
+        assert!(game_state.players.iter().all(|p| p.production.megacredits == 0 && p.production.heat == 0));
+        let (game_state, _) = basic_game(2);
+        assert!(game_state.players.iter().all(|p| p.production.megacredits == 1 && p.production.heat == 1));
+    }
+
+    #[test]
+    fn solo_games_start_at_14_with_two_neutral_cities() {
+        let (game_state, _) = basic_game(1);
+        assert!(game_state.solo);
+        assert_eq!(game_state.players[0].tf_rating, 14);
+        for tile_type in [TileType::City, TileType::Greenery] {
+            let neutral = game_state.tiles.iter().filter(|t| t.tile_type == tile_type && t.owner.is_none()).count();
+            assert_eq!(neutral, 2);
+        }
+        assert_eq!(game_state.count_tiles(&TileType::City), 2);
+    }
+
+    #[test]
+    fn only_solo_games_have_a_generation_limit() {
+        assert_eq!(basic_game(2).0.generation_limit(), None);
+        assert_eq!(basic_game(1).0.generation_limit(), Some(SOLO_GENERATIONS));
+        let (game_state, _) = action_game(1, &[Deck::Basic, Deck::Prelude]);
+        assert_eq!(game_state.generation_limit(), Some(SOLO_PRELUDE_GENERATIONS));
+    }
+
+
+    #[test]
+    fn corporations_with_an_unsupported_first_action_are_not_dealt() {
+        let decks = [Deck::Basic, Deck::Colonies, Deck::Corporate, Deck::Prelude, Deck::Promo, Deck::Venus];
+        let (game_state, _) = action_game(2, &decks);
+        let pile = &game_state.corporation_pile.draw_pile;
+        assert!(pile.iter().all(|c| !UNSUPPORTED_CORPORATIONS.contains(&c.name.as_str())));
+        assert!(pile.iter().any(|c| c.name == "Poseidon"));
+    }
+}
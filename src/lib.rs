@@ -1,3 +1,5 @@
 mod card;
+mod effect;
+mod event;
 
 pub use card::{Card, CSVCard};
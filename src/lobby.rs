@@ -0,0 +1,369 @@
+use std::sync::{Mutex, Arc, Condvar};
+use std::thread::spawn;
+use std::collections::VecDeque;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::card::{Card, Deck};
+use crate::commands::CannotExecute;
+use crate::game_state::GameState;
+use crate::server::{Request, Outgoing, Response, ErrorCode};
+use crate::state_machine::StateMachine;
+use crate::view::PlayerView;
+
+// the board has room for five players
+pub const MAX_PLAYERS: usize = 5;
+
+// requests with the uuid of the connection they came from
+pub type CmdDeque = Arc<(Mutex<VecDeque<(Uuid, Request)>>, Condvar)>;
+// serialized messages for all connections of a seat of a game
+pub type BroadcastDeque = Arc<(Mutex<VecDeque<(Uuid, usize, String)>>, Condvar)>;
+// serialized responses for the connection with the uuid
+pub type ResponseDeque = Arc<(Mutex<VecDeque<(Uuid, String)>>, Condvar)>;
+
+// chosen by the player who creates the game
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GameSettings {
+    pub player_count: usize,
+    pub decks: Vec<Deck>,
+    pub drafting: bool,
+}
+
+// a game that is waiting for players or already running
+pub struct Table {
+    pub id: Uuid,
+    pub settings: GameSettings,
+    // one join token per seat, the index is the player id
+    pub seats: Vec<Uuid>,
+    // seats are handed out in order, these are taken
+    pub seated: usize,
+    pub started: bool,
+    pub cmd_deque: CmdDeque,
+}
+
+// what the lobby lists about a game
+#[derive(Debug, Serialize)]
+pub struct GameInfo {
+    pub game_id: Uuid,
+    pub settings: GameSettings,
+    pub seated: usize,
+    pub started: bool,
+}
+
+pub struct Lobby {
+    cards: Vec<Card>,
+    tables: Vec<Table>,
+    broadcast_deque: BroadcastDeque,
+    response_deque: ResponseDeque,
+}
+
+impl Lobby {
+    pub fn new(cards: Vec<Card>, broadcast_deque: BroadcastDeque, response_deque: ResponseDeque) -> Lobby {
+        Lobby {
+            cards: cards,
+            tables: Vec::new(),
+            broadcast_deque: broadcast_deque,
+            response_deque: response_deque,
+        }
+    }
+
+    pub fn list(&self) -> Vec<GameInfo> {
+        self.tables.iter().map(|t| GameInfo {
+            game_id: t.id,
+            settings: t.settings.clone(),
+            seated: t.seated,
+            started: t.started,
+        }).collect()
+    }
+
+    pub fn create(&mut self, settings: GameSettings) -> Result<Uuid, String> {
+        if settings.player_count == 0 || settings.player_count > MAX_PLAYERS {
+            return Err(format!("A game needs 1 to {} players, not {}!", MAX_PLAYERS, settings.player_count));
+        }
+        if !settings.decks.contains(&Deck::Basic) {
+            return Err("The basic deck is required!".to_owned());
+        }
+        let table = Table {
+            id: Uuid::new_v4(),
+            seats: (0..settings.player_count).map(|_| Uuid::new_v4()).collect(),
+            settings: settings,
+            seated: 0,
+            started: false,
+            cmd_deque: Arc::new( (Mutex::new(VecDeque::new()), Condvar::new()) ),
+        };
+        let id = table.id;
+        self.tables.push(table);
+        Ok(id)
+    }
+
+    pub fn get_table(&self, game_id: Uuid) -> Result<&Table, String> {
+        match self.tables.iter().find(|t| t.id == game_id) {
+            Some(table) => Ok(table),
+            None => Err(format!("Game {} does not exist!", game_id)),
+        }
+    }
+
+    // Takes the next free seat, or the seat of the token when rejoining. Returns the player id and the token.
+    pub fn join(&mut self, game_id: Uuid, token: Option<Uuid>) -> Result<(usize, Uuid), String> {
+        let table = match self.tables.iter_mut().find(|t| t.id == game_id) {
+            Some(table) => table,
+            None => return Err(format!("Game {} does not exist!", game_id)),
+        };
+        match token {
+            Some(token) => match table.seats[..table.seated].iter().position(|seat| *seat == token) {
+                Some(player_id) => Ok((player_id, token)),
+                None => Err(format!("Unknown join token {} for game {}!", token, game_id)),
+            },
+            None if table.started || table.seated == table.seats.len() => Err(format!("Game {} has no free seat!", game_id)),
+            None => {
+                table.seated += 1;
+                Ok((table.seated - 1, table.seats[table.seated - 1]))
+            }
+        }
+    }
+
+    // deals the game once every seat is taken and runs it on its own thread
+    pub fn start(&mut self, game_id: Uuid) -> Result<(), String> {
+        let table = match self.tables.iter_mut().find(|t| t.id == game_id) {
+            Some(table) => table,
+            None => return Err(format!("Game {} does not exist!", game_id)),
+        };
+        if table.started {
+            return Err(format!("Game {} has already started!", game_id));
+        }
+        if table.seated < table.seats.len() {
+            return Err(format!("Game {} waits for {} more players!", game_id, table.seats.len() - table.seated));
+        }
+        let mut cards = self.cards.clone();
+        let state = GameState::new(cards.as_mut(), &table.settings.decks, table.settings.player_count, table.settings.drafting);
+        table.started = true;
+        let cmd_deque = Arc::clone(&table.cmd_deque);
+        let broadcast_deque = Arc::clone(&self.broadcast_deque);
+        let response_deque = Arc::clone(&self.response_deque);
+        println!("[LOG] Starting game {}", game_id);
+        // the state machine cannot be sent between threads, the game thread builds it
+        spawn(move || run_game(game_id, StateMachine::new(state, cards), cmd_deque, broadcast_deque, response_deque));
+        Ok(())
+    }
+}
+
+// all seats receive the state, without the secrets of the other players
+fn broadcast_state(game_id: Uuid, state_machine: &StateMachine, broadcast_deque: &BroadcastDeque) {
+    let (broadcast_lock, broadcast_cvar) = &**broadcast_deque;
+    let mut broadcast_deque = broadcast_lock.lock().unwrap();
+    for player in state_machine.get_state().players.iter() {
+        let view = PlayerView::new(state_machine.get_state(), player.id);
+        broadcast_deque.push_back((game_id, player.id, serde_json::to_string(&Outgoing::State(view)).unwrap()));
+    }
+    broadcast_cvar.notify_one();
+}
+
+// applies the requests of the game one after another
+fn run_game(game_id: Uuid, mut state_machine: StateMachine, cmd_deque: CmdDeque, broadcast_deque: BroadcastDeque, response_deque: ResponseDeque) {
+    if let Err(err) = state_machine.advance_phase() {
+        println!("[ERR] Could not set up game {}: {}", game_id, err);
+        return;
+    }
+    broadcast_state(game_id, &state_machine, &broadcast_deque);
+    let (deque_lock, deque_cvar) = &*cmd_deque;
+    let mut cmd_deque = deque_lock.lock().unwrap();
+    loop {
+        while let Some((uuid, request)) = cmd_deque.pop_front() {
+            let cmd_string = format!("{:?}", request.command);
+            let response = match state_machine.apply(request.command) {
+                Ok(()) => {
+                    println!("[LOG] Successfully applied {:?} in game {}", cmd_string, game_id);
+                    broadcast_state(game_id, &state_machine, &broadcast_deque);
+                    Response::ok(request.request_id)
+                }
+                Err(err) => {
+                    println!("[LOG] Encountered Error \"{}\" while applying {:?} in game {}", err, cmd_string, game_id);
+                    let code = match err.downcast_ref::<CannotExecute>() {
+                        Some(_) => ErrorCode::CannotExecute,
+                        None => ErrorCode::Internal,
+                    };
+                    Response::err(Some(request.request_id), code, err.to_string())
+                }
+            };
+            // only the sender of the command is told about the outcome
+            let (response_lock, response_cvar) = &*response_deque;
+            response_lock.lock().unwrap().push_back((uuid, serde_json::to_string(&Outgoing::Response(response)).unwrap()));
+            response_cvar.notify_one();
+        }
+        cmd_deque = deque_cvar.wait(cmd_deque).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::commands::{CmdWrapper, ChooseCorporation, ResearchCards};
+    use crate::test_utils::{basic_game, load_cards};
+
+    fn new_deque<T>() -> Arc<(Mutex<VecDeque<T>>, Condvar)> {
+        Arc::new( (Mutex::new(VecDeque::new()), Condvar::new()) )
+    }
+
+    // the game runs on its own thread, waits until it pushed the messages
+    fn wait_for<T>(deque: &Arc<(Mutex<VecDeque<T>>, Condvar)>, count: usize) -> Vec<T> {
+        let (deque_lock, deque_cvar) = &**deque;
+        let mut messages = deque_lock.lock().unwrap();
+        while messages.len() < count {
+            let (guard, timeout) = deque_cvar.wait_timeout(messages, Duration::from_secs(10)).unwrap();
+            assert!(!timeout.timed_out(), "Expected {} messages, got {}", count, guard.len());
+            messages = guard;
+        }
+        messages.drain(..).collect()
+    }
+
+    #[test]
+    fn every_seat_receives_the_state() {
+        let (state, cards) = basic_game(3);
+        let broadcast_deque: BroadcastDeque = new_deque();
+        let game_id = Uuid::new_v4();
+        broadcast_state(game_id, &StateMachine::new(state, cards), &broadcast_deque);
+        let messages = broadcast_deque.0.lock().unwrap();
+        let seats: Vec<(Uuid, usize)> = messages.iter().map(|(id, player_id, _)| (*id, *player_id)).collect();
+        assert_eq!(seats, vec![(game_id, 0), (game_id, 1), (game_id, 2)]);
+        for (_, _, msg) in messages.iter() {
+            let value: serde_json::Value = serde_json::from_str(msg).unwrap();
+            assert!(value.get("State").is_some());
+        }
+    }
+
+    #[test]
+    fn the_sender_gets_the_response_and_all_seats_the_new_state() {
+        let cmd_deque: CmdDeque = new_deque();
+        let broadcast_deque: BroadcastDeque = new_deque();
+        let response_deque: ResponseDeque = new_deque();
+        let mut cards = load_cards();
+        let state = GameState::new(cards.as_mut(), &vec![Deck::Basic], 2, false);
+        let game_id = Uuid::new_v4();
+        let deques = (Arc::clone(&cmd_deque), Arc::clone(&broadcast_deque), Arc::clone(&response_deque));
+        spawn(move || run_game(game_id, StateMachine::new(state, cards), deques.0, deques.1, deques.2));
+        let setup = wait_for(&broadcast_deque, 2);
+        let view: serde_json::Value = serde_json::from_str(&setup[0].2).unwrap();
+        let corporation = view.pointer("/State/players/0/Own/hand/0/id").unwrap().as_str().unwrap().to_owned();
+
+        let sender = Uuid::new_v4();
+        for (request_id, card_id) in [(1, "unknown".to_owned()), (2, corporation)] {
+            let command = CmdWrapper::ChooseCorporation(ChooseCorporation{player_id: 0, card_id, rejected: 0});
+            cmd_deque.0.lock().unwrap().push_back((sender, Request{request_id, command}));
+            cmd_deque.1.notify_one();
+        }
+        let responses = wait_for(&response_deque, 2);
+        assert!(responses.iter().all(|(uuid, _)| *uuid == sender));
+        let results: Vec<serde_json::Value> = responses.iter().map(|(_, msg)| serde_json::from_str(msg).unwrap()).collect();
+        assert_eq!(results[0].pointer("/Response/request_id"), Some(&serde_json::json!(1)));
+        assert_eq!(results[0].pointer("/Response/result/Err/code"), Some(&serde_json::json!("CannotExecute")));
+        assert_eq!(results[1].pointer("/Response/result"), Some(&serde_json::json!({"Ok": null})));
+        // only the applied command is broadcast
+        assert_eq!(wait_for(&broadcast_deque, 2).len(), 2);
+    }
+
+    fn new_lobby(cards: Vec<Card>) -> Lobby {
+        Lobby::new(cards, new_deque(), new_deque())
+    }
+
+    fn settings(player_count: usize) -> GameSettings {
+        GameSettings{player_count, decks: vec![Deck::Basic], drafting: false}
+    }
+
+    #[test]
+    fn seats_are_handed_out_in_order_and_resumed_with_their_token() {
+        let mut lobby = new_lobby(Vec::new());
+        let game_id = lobby.create(settings(2)).unwrap();
+        let (first, first_token) = lobby.join(game_id, None).unwrap();
+        let (second, second_token) = lobby.join(game_id, None).unwrap();
+        assert_eq!((first, second), (0, 1));
+        assert_ne!(first_token, second_token);
+        assert!(lobby.join(game_id, None).is_err());
+        assert_eq!(lobby.join(game_id, Some(second_token)), Ok((1, second_token)));
+        assert_eq!(lobby.join(game_id, Some(first_token)), Ok((0, first_token)));
+    }
+
+    #[test]
+    fn unknown_tokens_and_games_are_rejected() {
+        let mut lobby = new_lobby(Vec::new());
+        let game_id = lobby.create(settings(2)).unwrap();
+        assert!(lobby.join(game_id, Some(Uuid::new_v4())).is_err());
+        // the token of a free seat does not take it
+        let free_seat = lobby.get_table(game_id).unwrap().seats[0];
+        assert!(lobby.join(game_id, Some(free_seat)).is_err());
+        assert!(lobby.join(Uuid::new_v4(), None).is_err());
+        assert_eq!(lobby.get_table(game_id).unwrap().seated, 0);
+    }
+
+    #[test]
+    fn games_need_1_to_5_players_and_the_basic_deck() {
+        let mut lobby = new_lobby(Vec::new());
+        assert!(lobby.create(settings(0)).is_err());
+        assert!(lobby.create(settings(MAX_PLAYERS + 1)).is_err());
+        assert!(lobby.create(GameSettings{player_count: 2, decks: vec![Deck::Venus], drafting: false}).is_err());
+        let game_id = lobby.create(settings(MAX_PLAYERS)).unwrap();
+        let games = lobby.list();
+        assert_eq!(games.len(), 1);
+        assert_eq!((games[0].game_id, games[0].seated, games[0].started), (game_id, 0, false));
+    }
+
+    #[test]
+    fn games_start_once_every_seat_is_taken() {
+        let mut lobby = new_lobby(load_cards());
+        let game_id = lobby.create(settings(2)).unwrap();
+        let (_, token) = lobby.join(game_id, None).unwrap();
+        assert!(lobby.start(game_id).is_err());
+        lobby.join(game_id, None).unwrap();
+        lobby.start(game_id).unwrap();
+        assert!(lobby.get_table(game_id).unwrap().started);
+        assert!(lobby.start(game_id).is_err());
+        assert!(lobby.start(Uuid::new_v4()).is_err());
+        // seats are only resumed once the game runs
+        assert!(lobby.join(game_id, None).is_err());
+        assert_eq!(lobby.join(game_id, Some(token)), Ok((0, token)));
+    }
+
+    #[test]
+    fn started_games_broadcast_the_setup() {
+        let mut lobby = new_lobby(load_cards());
+        let broadcast_deque = Arc::clone(&lobby.broadcast_deque);
+        let game_id = lobby.create(settings(1)).unwrap();
+        lobby.join(game_id, None).unwrap();
+        lobby.start(game_id).unwrap();
+        let messages = wait_for(&broadcast_deque, 1);
+        assert_eq!((messages[0].0, messages[0].1), (game_id, 0));
+    }
+
+    #[test]
+    fn lobby_games_reach_the_action_phase_by_commands() {
+        let mut lobby = new_lobby(load_cards());
+        let broadcast_deque = Arc::clone(&lobby.broadcast_deque);
+        let response_deque = Arc::clone(&lobby.response_deque);
+        let game_id = lobby.create(settings(2)).unwrap();
+        for _ in 0..2 {
+            lobby.join(game_id, None).unwrap();
+        }
+        lobby.start(game_id).unwrap();
+        let cmd_deque = Arc::clone(&lobby.get_table(game_id).unwrap().cmd_deque);
+        let sender = Uuid::new_v4();
+        for (_, player_id, msg) in wait_for(&broadcast_deque, 2) {
+            let view: serde_json::Value = serde_json::from_str(&msg).unwrap();
+            let card_id = view.pointer(&format!("/State/players/{}/Own/hand/0/id", player_id)).unwrap().as_str().unwrap().to_owned();
+            let commands = vec![
+                CmdWrapper::ChooseCorporation(ChooseCorporation{player_id, card_id, rejected: 0}),
+                CmdWrapper::ResearchCards(ResearchCards{player_id, card_ids: Vec::new()}),
+            ];
+            for (idx, command) in commands.into_iter().enumerate() {
+                cmd_deque.0.lock().unwrap().push_back((sender, Request{request_id: (2 * player_id + idx) as u64, command}));
+                cmd_deque.1.notify_one();
+            }
+        }
+        for (_, msg) in wait_for(&response_deque, 4) {
+            let response: serde_json::Value = serde_json::from_str(&msg).unwrap();
+            assert_eq!(response.pointer("/Response/result"), Some(&serde_json::json!({"Ok": null})));
+        }
+        let (_, _, msg) = wait_for(&broadcast_deque, 8).pop().unwrap();
+        let view: serde_json::Value = serde_json::from_str(&msg).unwrap();
+        assert_eq!(view.pointer("/State/phase"), Some(&serde_json::json!("Action")));
+    }
+}
@@ -1,23 +1,28 @@
 use std::path::Path;
 use std::fs::File;
 use std::io::prelude::*;
-use std::thread::spawn;
-use std::sync::{Arc};
 
 mod state_machine;
 mod card;
+mod effect;
+mod event;
 mod commands;
 mod game_state;
 mod player;
 mod card_pile;
+mod board;
+mod colony;
+mod score;
 mod server;
+mod view;
+mod lobby;
+#[cfg(test)]
+mod test_utils;
 
-use crate::state_machine::StateMachine;
-use crate::game_state::{GameState, Phase};
-use crate::card::Deck;
-use crate::commands::{ChooseCorporation, ResearchCards, PlayCard, CmdWrapper};
 use crate::server::Server;
 
+const DEFAULT_ADDRESS: &str = "127.0.0.1:9001";
+
 fn main() {
     // load cards
     let mut all_cards = Vec::<>::new();
@@ -33,60 +38,10 @@ fn main() {
             }
         }
     }
-    // init game
-    let used_decks = vec![Deck::Basic];
-    let my_state = GameState::new(all_cards.as_mut(), used_decks.as_ref(), 2);
-    let mut state_machine = StateMachine::new(my_state, all_cards);
-    state_machine.advance_phase().unwrap();
-
-    for player_id in 0..2 {
-        let card = state_machine.get_state().get_player(player_id).unwrap().hand.first().unwrap();
-        let cmd = ChooseCorporation{player_id: player_id, card_id: card.id.to_owned()};
-        state_machine.apply(CmdWrapper::ChooseCorporation(cmd)).unwrap();
-        let mut card_ids: Vec<String> = state_machine.get_state().players[player_id].research_queue.iter().map(|c| c.id.to_owned()).collect();
-        let research_ids = card_ids.split_off(card_ids.len() / 2);
-        state_machine.apply(CmdWrapper::ResearchCards(ResearchCards{player_id: player_id, card_ids: research_ids})).unwrap();
-        println!("{:?}", state_machine.get_state().players[player_id].corporation.as_ref().unwrap().production);
-        println!("{:?}", state_machine.get_state().players[player_id].production);
-    }
-    // advance to action phase
-    state_machine.advance_phase().unwrap();
-    let active_p_id = state_machine.get_state().active_player;
-    let card_id = state_machine.get_state().players[active_p_id].hand[0].id.to_owned();
-    match state_machine.apply(CmdWrapper::PlayCard(PlayCard{owner_id: active_p_id, card_id: card_id, target_id: None})) {
-        Ok(()) => (),
-        Err(err) => println!("\nERROR: {}\n", err),
-    };
-    // test advancing to production phase
-    state_machine.advance_phase().unwrap();
-    // after production state should automatically transition into research phase
-    assert_eq!(state_machine.get_state().phase, Phase::Research);
-    // println!("{:?}", state_machine.get_state().players[0]);
-    
-    println!("{:?}", state_machine.get_state().players[0].research_queue);
-
-    let server = Server::new();
-    let arc_cmd_deque = Arc::clone(&server.cmd_deque);
-
-    let is_running = true;
-    let svr_handle = spawn(move || {
-        server.start();
-    });
-
-    let (deque_lock, deque_cvar) = &*arc_cmd_deque;
-    let mut cmd_deque = deque_lock.lock().unwrap();
-    while is_running {
-        while !cmd_deque.is_empty() {
-            let cmd = cmd_deque.pop_front().unwrap();
-            let cmd_string = format!("{:?}", cmd);
-            match state_machine.apply(cmd) {
-                Ok(()) => println!("[LOG] Successfully applied {:?}", cmd_string),
-                // TODO broadcast state change to all clients
-                Err(err) => println!("[LOG] Encountered Error \"{}\" while applying {:?}", err, cmd_string),
-            }
-            deque_cvar.notify_one();
-        }
-        cmd_deque = deque_cvar.wait(cmd_deque).unwrap();
-    }
-    svr_handle.join().unwrap();
+    // the address can be passed as the first argument
+    let address = std::env::args().nth(1).unwrap_or(DEFAULT_ADDRESS.to_owned());
+    println!("[LOG] Listening on {}", address);
+    // games are created, joined and started through the lobby
+    let server = Server::new(&address, all_cards);
+    server.start();
 }
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 
-use crate::card::Card;
+use crate::card::{Card, Resource};
+use crate::effect::TilePlacement;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Player {
@@ -12,9 +13,19 @@ pub struct Player {
     pub production: Production,
     pub hand: Vec<Card>,
     pub research_queue: Vec<Card>,
+    // the cards the player picks from when drafting
+    pub draft_hand: Vec<Card>,
+    // the kept preludes, played at the start of the first generation
+    pub preludes: Vec<Card>,
+    // tiles the player still has to place, e.g. from a played card
+    pub pending_tiles: Vec<TilePlacement>,
+    // the corporation's first action has to be taken before any other action
+    pub pending_first_action: bool,
+    // cards the player has to discard from the hand, e.g. after the Pluto colony bonus
+    pub pending_discards: u32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum ActionState {
     Acting(u8), Waiting, Passed,
 }
@@ -39,7 +50,72 @@ pub struct Production {
     pub heat: u32
 }
 
+pub const START_TF_RATING: i32 = 20;
+// a solo game starts with a lower terraform rating
+pub const SOLO_TF_RATING: i32 = 14;
+// each turn a player takes one or two actions
+pub const ACTIONS_PER_TURN: u8 = 2;
+pub const PLANTS_PER_GREENERY: u32 = 8;
+pub const HEAT_PER_TEMPERATURE: u32 = 8;
+// steel pays for building tags, titanium for space tags
+pub const STEEL_VALUE: u32 = 2;
+pub const TITANIUM_VALUE: u32 = 3;
+// without the Corporate Era every production starts at 1
+pub const BASIC_PRODUCTION: u32 = 1;
+// megacredit production may drop to -5, all other productions to 0
+pub const MIN_MEGACREDIT_PRODUCTION: i32 = -5;
+
 impl Player {
+    // Ecoline needs only 7 plants for a greenery
+    pub fn plants_per_greenery(&self) -> u32 {
+        match self.corporation.as_ref().map(|c| c.name.as_ref()) {
+            Some("Ecoline") => 7,
+            _ => PLANTS_PER_GREENERY,
+        }
+    }
+
+    // PhoboLog's titanium is worth 1 MC extra
+    pub fn titanium_bonus(&self) -> u32 {
+        match self.corporation.as_ref().map(|c| c.name.as_ref()) {
+            Some("PhoboLog") => 1,
+            _ => 0,
+        }
+    }
+
+    // how much of the resource the player has in the inventory
+    pub fn inventory_count(&self, resource: &Resource) -> u32 {
+        match resource {
+            Resource::MegaCredits(_) => self.inventory.megacredits,
+            Resource::Steel(_) => self.inventory.steel,
+            Resource::Titanium(_) => self.inventory.titanium,
+            Resource::Plants(_) => self.inventory.plants,
+            Resource::Energy(_) => self.inventory.energy,
+            Resource::Heat(_) => self.inventory.heat,
+            Resource::Special => 0,
+        }
+    }
+
+    // how much of the resource the player produces
+    pub fn production_count(&self, resource: &Resource) -> i32 {
+        match resource {
+            Resource::MegaCredits(_) => self.production.megacredits,
+            Resource::Steel(_) => self.production.steel as i32,
+            Resource::Titanium(_) => self.production.titanium as i32,
+            Resource::Plants(_) => self.production.plants as i32,
+            Resource::Energy(_) => self.production.energy as i32,
+            Resource::Heat(_) => self.production.heat as i32,
+            Resource::Special => 0,
+        }
+    }
+
+    // the lowest the production of the resource may drop
+    pub fn min_production(resource: &Resource) -> i32 {
+        match resource {
+            Resource::MegaCredits(_) => MIN_MEGACREDIT_PRODUCTION,
+            _ => 0,
+        }
+    }
+
     pub fn enqueue_research(&mut self, projects: &mut Vec<Card>) -> () {
         self.research_queue.append(projects);
     }
@@ -48,10 +124,14 @@ impl Player {
         self.hand.append(corporations);
     }
 
-    pub fn new(id: usize) -> Player {
+    pub fn draft_preludes(&mut self, preludes: &mut Vec<Card>) -> () {
+        self.hand.append(preludes);
+    }
+
+    pub fn new(id: usize, start_production: u32) -> Player {
         Player {
             id: id,
-            tf_rating: 20,
+            tf_rating: START_TF_RATING,
             action_state: ActionState::Waiting,
             corporation: None,
             inventory: Inventory {
@@ -63,15 +143,20 @@ impl Player {
                 heat: 0,
             },
             production: Production {
-                megacredits: 1,
-                steel: 1,
-                titanium: 1,
-                plants: 1,
-                energy: 1,
-                heat: 1,
+                megacredits: start_production as i32,
+                steel: start_production,
+                titanium: start_production,
+                plants: start_production,
+                energy: start_production,
+                heat: start_production,
             },
             hand: Vec::new(),
             research_queue: Vec::new(),
+            draft_hand: Vec::new(),
+            preludes: Vec::new(),
+            pending_tiles: Vec::new(),
+            pending_first_action: false,
+            pending_discards: 0,
         }
     }
 }
@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+
+use crate::card::TileType;
+use crate::game_state::GameState;
+use crate::board;
+
+pub const MILESTONE_VP: i32 = 5;
+pub const FIRST_PLACE_VP: i32 = 5;
+pub const SECOND_PLACE_VP: i32 = 2;
+
+// one row of the scoresheet
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FinalScore {
+    pub player_id: usize,
+    pub tf_rating: i32,
+    pub milestones: i32,
+    pub awards: i32,
+    pub greeneries: i32,
+    pub cities: i32,
+    pub cards: i32,
+    pub total: i32,
+}
+
+pub fn final_scores(game_state: &GameState) -> Vec<FinalScore> {
+    game_state.players.iter().map(|player| {
+        let mut score = FinalScore {
+            player_id: player.id,
+            tf_rating: player.tf_rating,
+            milestones: milestone_points(game_state, player.id),
+            awards: award_points(game_state, player.id),
+            greeneries: greenery_points(game_state, player.id),
+            cities: city_points(game_state, player.id),
+            cards: card_points(game_state, player.id),
+            total: 0,
+        };
+        score.total = score.tf_rating + score.milestones + score.awards + score.greeneries + score.cities + score.cards;
+        score
+    }).collect()
+}
+
+fn milestone_points(game_state: &GameState, player_id: usize) -> i32 {
+    game_state.milestones.iter().filter(|m| m.owner == Some(player_id)).count() as i32 * MILESTONE_VP
+}
+
+// The best player gets 5 VP, the second best 2 VP. Ties share the place,
+// a tie for the first place means there is no second place. With 2 players only the first place is awarded.
+fn award_points(game_state: &GameState, player_id: usize) -> i32 {
+    let mut points = 0;
+    for award in game_state.awards.iter().filter(|a| a.owner.is_some()) {
+        let mut metrics: Vec<i32> = game_state.players.iter().map(|p| award.name.metric(game_state, p.id)).collect();
+        let own = metrics[player_id];
+        metrics.sort_unstable_by(|a, b| b.cmp(a));
+        let first = metrics[0];
+        if own == first {
+            points += FIRST_PLACE_VP;
+        } else if game_state.players.len() > 2 && metrics.iter().filter(|m| **m == first).count() == 1 && own == metrics[1] {
+            points += SECOND_PLACE_VP;
+        }
+    }
+    points
+}
+
+fn greenery_points(game_state: &GameState, player_id: usize) -> i32 {
+    game_state.tiles.iter().filter(|t| t.owner == Some(player_id) && t.tile_type == TileType::Greenery).count() as i32
+}
+
+// each city scores 1 VP per adjacent greenery, regardless of who owns the greenery
+fn city_points(game_state: &GameState, player_id: usize) -> i32 {
+    game_state.tiles.iter()
+        .filter(|t| t.owner == Some(player_id) && (t.tile_type == TileType::City || t.tile_type == TileType::Capital))
+        .map(|city| board::neighbours(&game_state.tiles, city).iter().filter(|n| n.tile_type == TileType::Greenery).count())
+        .sum::<usize>() as i32
+}
+
+fn card_points(game_state: &GameState, player_id: usize) -> i32 {
+    game_state.cards_in_play.iter().filter(|c| c.owner == player_id).map(|c| c.victory_points(game_state)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::Awards;
+    use crate::test_utils::{basic_game, put_in_play};
+
+    fn occupy(game_state: &mut GameState, x: u32, z: u32, tile_type: TileType, owner: usize) {
+        let tile = game_state.tiles.iter_mut().find(|t| t.x == x && t.z == z).unwrap();
+        tile.tile_type = tile_type;
+        tile.owner = Some(owner);
+    }
+
+    #[test]
+    fn cities_score_all_adjacent_greeneries() {
+        let (mut game_state, _) = basic_game(2);
+        occupy(&mut game_state, 4, 4, TileType::City, 0);
+        occupy(&mut game_state, 4, 5, TileType::Greenery, 0);
+        occupy(&mut game_state, 3, 4, TileType::Greenery, 1);
+        occupy(&mut game_state, 7, 0, TileType::Greenery, 1);
+        let scores = final_scores(&game_state);
+        assert_eq!((scores[0].greeneries, scores[0].cities), (1, 2));
+        assert_eq!((scores[1].greeneries, scores[1].cities), (2, 0));
+    }
+
+    #[test]
+    fn milestones_and_cards_add_to_the_rating() {
+        let (mut game_state, cards) = basic_game(2);
+        game_state.milestones[0].owner = Some(0);
+        put_in_play(&mut game_state, &cards, 0, "Colonizer Training Camp");
+        let birds = put_in_play(&mut game_state, &cards, 0, "Birds");
+        game_state.cards_in_play.iter_mut().find(|c| c.card.id == birds).unwrap().resources = 3;
+        let scores = final_scores(&game_state);
+        assert_eq!(scores[0].milestones, MILESTONE_VP);
+        assert_eq!(scores[0].cards, 5);
+        assert_eq!(scores[0].total, 20 + MILESTONE_VP + 5);
+        assert_eq!(scores[1].total, 20);
+    }
+
+
+    fn fund(game_state: &mut GameState, award: Awards, owner: usize) {
+        game_state.awards.iter_mut().find(|a| a.name == award).unwrap().owner = Some(owner);
+    }
+
+    #[test]
+    fn tied_players_share_the_first_place_in_a_2_player_game() {
+        let (mut game_state, _) = basic_game(2);
+        fund(&mut game_state, Awards::Thermalist, 1);
+        game_state.players[0].inventory.heat = 4;
+        game_state.players[1].inventory.heat = 4;
+        let scores = final_scores(&game_state);
+        assert_eq!((scores[0].awards, scores[1].awards), (FIRST_PLACE_VP, FIRST_PLACE_VP));
+    }
+
+    #[test]
+    fn there_is_no_second_place_in_a_2_player_game() {
+        let (mut game_state, _) = basic_game(2);
+        fund(&mut game_state, Awards::Thermalist, 1);
+        game_state.players[0].inventory.heat = 5;
+        game_state.players[1].inventory.heat = 4;
+        let scores = final_scores(&game_state);
+        assert_eq!((scores[0].awards, scores[1].awards), (FIRST_PLACE_VP, 0));
+    }
+
+    #[test]
+    fn a_tie_for_the_first_place_leaves_no_second_place() {
+        let (mut game_state, _) = basic_game(3);
+        fund(&mut game_state, Awards::Miner, 2);
+        game_state.players[0].inventory.steel = 3;
+        game_state.players[1].inventory.titanium = 3;
+        game_state.players[2].inventory.steel = 1;
+        let scores: Vec<i32> = final_scores(&game_state).iter().map(|s| s.awards).collect();
+        assert_eq!(scores, vec![FIRST_PLACE_VP, FIRST_PLACE_VP, 0]);
+    }
+
+    #[test]
+    fn tied_players_share_the_second_place() {
+        let (mut game_state, _) = basic_game(3);
+        fund(&mut game_state, Awards::Miner, 2);
+        game_state.players[0].inventory.steel = 3;
+        game_state.players[1].inventory.titanium = 1;
+        game_state.players[2].inventory.steel = 1;
+        let scores: Vec<i32> = final_scores(&game_state).iter().map(|s| s.awards).collect();
+        assert_eq!(scores, vec![FIRST_PLACE_VP, SECOND_PLACE_VP, SECOND_PLACE_VP]);
+    }
+}
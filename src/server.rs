@@ -1,49 +1,145 @@
 use std::net::{TcpListener, TcpStream};
 use std::sync::{Mutex, Arc, Condvar};
 use std::thread::spawn;
-use std::collections::VecDeque;
+use std::collections::{VecDeque, HashMap};
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use tungstenite::server::accept;
-use tungstenite::protocol::{Message, WebSocket};
+use tungstenite::protocol::{Message, WebSocket, Role};
 
+use crate::card::Card;
 use crate::commands::CmdWrapper;
-use crate::game_state::GameState;
+use crate::lobby::{Lobby, GameSettings, GameInfo, CmdDeque, BroadcastDeque, ResponseDeque};
+use crate::view::PlayerView;
+
+// messages the clients send to the server
+#[derive(Debug, Deserialize)]
+pub enum Incoming {
+    CreateGame(GameSettings),
+    ListGames,
+    // Binds the connection to a seat of the game, has to be sent before any request.
+    // Without a token the next free seat is taken, with the token of a seat it is resumed.
+    Join { game_id: Uuid, token: Option<Uuid> },
+    // any seated player may start the game once all seats are taken
+    StartGame { game_id: Uuid },
+    Request(Request),
+}
+
+// a command sent by a client, the request id is returned in the response
+#[derive(Debug, Deserialize)]
+pub struct Request {
+    pub request_id: u64,
+    pub command: CmdWrapper,
+}
+
+// messages the server sends to the clients
+#[derive(Serialize)]
+pub enum Outgoing<'a> {
+    // every player gets their own view of the state
+    State(PlayerView<'a>),
+    Response(Response),
+    Games(Vec<GameInfo>),
+    Created { game_id: Uuid },
+    // the connection plays the seat of this player, the token resumes the seat after a disconnect
+    Joined { game_id: Uuid, player_id: usize, token: Uuid },
+    Started { game_id: Uuid },
+}
+
+// The reply to a request. The request id is missing if the message could not be parsed.
+#[derive(Debug, Serialize)]
+pub struct Response {
+    pub request_id: Option<u64>,
+    pub result: Result<(), ErrorReply>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorReply {
+    pub code: ErrorCode,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub enum ErrorCode {
+    // the message is no valid request
+    InvalidMessage,
+    // the connection has not joined a seat, or the command is for another player
+    Unauthorized,
+    // the lobby cannot serve the request, e.g. the game is full or has not started yet
+    Lobby,
+    // the command was rejected by the rules, see CannotExecute
+    CannotExecute,
+    // any other error while applying the command
+    Internal,
+}
+
+impl Response {
+    pub fn ok(request_id: u64) -> Response {
+        Response{request_id: Some(request_id), result: Ok(())}
+    }
+
+    pub fn err(request_id: Option<u64>, code: ErrorCode, reason: String) -> Response {
+        Response{request_id: request_id, result: Err(ErrorReply{code: code, reason: reason})}
+    }
+}
+
+// The messages waiting to be written to one client. A closed outbox stops its writer.
+pub struct Outbox {
+    pub messages: VecDeque<String>,
+    pub open: bool,
+}
+
+// a seat is the player id within a game
+pub type Seat = (Uuid, usize);
+// an open connection, the seat is unknown until the client joined a game
+pub type ConnectionEntry = (Option<Seat>, Uuid, Arc<(Mutex<Outbox>, Condvar)>);
 
 pub struct Server {
     tcp_listener: TcpListener,
-    pub cmd_deque: Arc<(Mutex<VecDeque<CmdWrapper>>, Condvar)>,
-    pub broadcast_deque: Arc<(Mutex<VecDeque<GameState>>, Condvar)>,
-    pub error_deque: Arc<(Mutex<VecDeque<(CmdWrapper, String)>>, Condvar)>,
-    pub connections: Arc<Mutex<Vec<(usize, Uuid)>>>,
+    pub lobby: Arc<Mutex<Lobby>>,
+    // e.g. the state of a game after each command
+    pub broadcast_deque: BroadcastDeque,
+    pub response_deque: ResponseDeque,
+    pub connections: Arc<Mutex<Vec<ConnectionEntry>>>,
+    // the last state sent to each seat, handed to connections that join or rejoin the seat
+    pub last_states: Arc<Mutex<HashMap<Seat, String>>>,
 }
 
 impl Server {
-    pub fn new() -> Server {
+    pub fn new(address: &str, cards: Vec<Card>) -> Server {
+        let broadcast_deque: BroadcastDeque = Arc::new( (Mutex::new(VecDeque::new()), Condvar::new()) );
+        let response_deque: ResponseDeque = Arc::new( (Mutex::new(VecDeque::new()), Condvar::new()) );
         Server {
-            tcp_listener: TcpListener::bind("127.0.0.1:9001").unwrap(),
-            cmd_deque: Arc::new( (Mutex::new(VecDeque::new()), Condvar::new()) ),
-            broadcast_deque: Arc::new( (Mutex::new(VecDeque::new()), Condvar::new()) ),
-            error_deque: Arc::new( (Mutex::new(VecDeque::new()), Condvar::new()) ),
+            tcp_listener: TcpListener::bind(address).unwrap(),
+            lobby: Arc::new(Mutex::new(Lobby::new(cards, Arc::clone(&broadcast_deque), Arc::clone(&response_deque)))),
+            broadcast_deque: broadcast_deque,
+            response_deque: response_deque,
             connections: Arc::new(Mutex::new(Vec::new())),
+            last_states: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
     pub fn start(&self) {
-        // A WebSocket echo server
+        self.start_broadcast();
+        self.start_responses();
         for stream in self.tcp_listener.incoming() {
-            let arc_cmd_deque = Arc::clone(&self.cmd_deque);
+            let arc_lobby = Arc::clone(&self.lobby);
             let arc_connections = Arc::clone(&self.connections);
+            let arc_last_states = Arc::clone(&self.last_states);
             spawn (move || {
-                let websocket = accept(stream.unwrap()).unwrap();
-                let player_uuid = Uuid::new_v4();
-                let player_id;
-                {
-                    let mut connections = arc_connections.lock().unwrap();
-                    player_id = connections.len();
-                    connections.push((player_id, player_uuid));
-                }
-                let mut connection = Connection::new(websocket, player_id, player_uuid, arc_cmd_deque);
-                println!("[LOG] New connection player_id: {}, uuid: {}", player_id, player_uuid);
+                let stream = stream.unwrap();
+                // the writer gets its own handle on the stream, so it does not wait for the blocking reads
+                let write_stream = stream.try_clone().unwrap();
+                let websocket = accept(stream).unwrap();
+                let connection_uuid = Uuid::new_v4();
+                let outbox = Arc::new( (Mutex::new(Outbox{messages: VecDeque::new(), open: true}), Condvar::new()) );
+                arc_connections.lock().unwrap().push((None, connection_uuid, Arc::clone(&outbox)));
+                let writer = WebSocket::from_raw_socket(write_stream, Role::Server, None);
+                let writer_outbox = Arc::clone(&outbox);
+                spawn(move || write_outbox(writer, writer_outbox));
+                let mut connection = Connection::new(
+                    websocket, connection_uuid, arc_lobby, Arc::clone(&outbox), Arc::clone(&arc_connections), arc_last_states
+                );
+                println!("[LOG] New connection uuid: {}", connection_uuid);
                 let mut connection_open = true;
                 while connection_open {
                     match connection.handle_read() {
@@ -51,38 +147,180 @@ impl Server {
                         Err(_) => connection_open = false,
                     }
                 }
+                // the seat stays reserved, the player may rejoin with the same token
+                arc_connections.lock().unwrap().retain(|(_, uuid, _)| *uuid != connection_uuid);
+                let (outbox_lock, outbox_cvar) = &*outbox;
+                outbox_lock.lock().unwrap().open = false;
+                outbox_cvar.notify_one();
             });
         }
     }
+
+    // hands every broadcast message to the outboxes of the seat's open connections
+    fn start_broadcast(&self) {
+        let arc_broadcast_deque = Arc::clone(&self.broadcast_deque);
+        let arc_connections = Arc::clone(&self.connections);
+        let arc_last_states = Arc::clone(&self.last_states);
+        spawn(move || {
+            let (deque_lock, deque_cvar) = &*arc_broadcast_deque;
+            let mut broadcast_deque = deque_lock.lock().unwrap();
+            loop {
+                while let Some((game_id, player_id, msg)) = broadcast_deque.pop_front() {
+                    let seat = (game_id, player_id);
+                    // joining connections get either the message or the stored state, see Connection::join
+                    let connections = arc_connections.lock().unwrap();
+                    for (_, _, outbox) in connections.iter().filter(|(s, _, _)| *s == Some(seat)) {
+                        push_outbox(outbox, msg.to_owned());
+                    }
+                    arc_last_states.lock().unwrap().insert(seat, msg);
+                }
+                broadcast_deque = deque_cvar.wait(broadcast_deque).unwrap();
+            }
+        });
+    }
+
+    // hands every response to the outbox of the connection that sent the request
+    fn start_responses(&self) {
+        let arc_response_deque = Arc::clone(&self.response_deque);
+        let arc_connections = Arc::clone(&self.connections);
+        spawn(move || {
+            let (deque_lock, deque_cvar) = &*arc_response_deque;
+            let mut response_deque = deque_lock.lock().unwrap();
+            loop {
+                while let Some((uuid, msg)) = response_deque.pop_front() {
+                    if let Some((_, _, outbox)) = arc_connections.lock().unwrap().iter().find(|(_, id, _)| *id == uuid) {
+                        push_outbox(outbox, msg);
+                    }
+                }
+                response_deque = deque_cvar.wait(response_deque).unwrap();
+            }
+        });
+    }
+}
+
+// messages for closed connections are dropped
+fn push_outbox(outbox: &Arc<(Mutex<Outbox>, Condvar)>, msg: String) {
+    let (outbox_lock, outbox_cvar) = &**outbox;
+    let mut outbox = outbox_lock.lock().unwrap();
+    if outbox.open {
+        outbox.messages.push_back(msg);
+        outbox_cvar.notify_one();
+    }
+}
+
+// writes the messages of the outbox to the socket until the connection is closed
+fn write_outbox(mut socket: WebSocket<TcpStream>, outbox: Arc<(Mutex<Outbox>, Condvar)>) {
+    let (outbox_lock, outbox_cvar) = &*outbox;
+    let mut outbox = outbox_lock.lock().unwrap();
+    while outbox.open {
+        while let Some(msg) = outbox.messages.pop_front() {
+            if let Err(err) = socket.write_message(Message::Text(msg)) {
+                println!("[ERR] Failed to write message {:?}", err);
+                outbox.open = false;
+                return;
+            }
+        }
+        outbox = outbox_cvar.wait(outbox).unwrap();
+    }
 }
 
 pub struct Connection {
     pub socket: WebSocket<TcpStream>,
-    pub player_id: usize,
-    pub player_uuid: Uuid,
-    pub arc_cmd_deque: Arc<(Mutex<VecDeque<CmdWrapper>>, Condvar)>,
+    // the seat the connection joined
+    pub seat: Option<Seat>,
+    pub connection_uuid: Uuid,
+    pub arc_lobby: Arc<Mutex<Lobby>>,
+    pub outbox: Arc<(Mutex<Outbox>, Condvar)>,
+    pub arc_connections: Arc<Mutex<Vec<ConnectionEntry>>>,
+    pub last_states: Arc<Mutex<HashMap<Seat, String>>>,
 }
 
 impl Connection {
     pub fn new(
             socket: WebSocket<TcpStream>,
-            id: usize,
             uuid: Uuid,
-            arc_cmd_deque: Arc<(Mutex<VecDeque<CmdWrapper>>, Condvar)>
+            arc_lobby: Arc<Mutex<Lobby>>,
+            outbox: Arc<(Mutex<Outbox>, Condvar)>,
+            arc_connections: Arc<Mutex<Vec<ConnectionEntry>>>,
+            last_states: Arc<Mutex<HashMap<Seat, String>>>
     ) -> Connection {
         Connection {
             socket: socket,
-            player_id: id,
-            player_uuid: uuid,
-            arc_cmd_deque: arc_cmd_deque,
+            seat: None,
+            connection_uuid: uuid,
+            arc_lobby: arc_lobby,
+            outbox: outbox,
+            arc_connections: arc_connections,
+            last_states: last_states,
+        }
+    }
+
+    fn reply(&self, msg: &Outgoing) {
+        push_outbox(&self.outbox, serde_json::to_string(msg).unwrap());
+    }
+
+    fn reject(&self, request_id: Option<u64>, code: ErrorCode, reason: String) {
+        println!("[ERR] {}", reason);
+        self.reply(&Outgoing::Response(Response::err(request_id, code, reason)));
+    }
+
+    // binds the connection to the seat and sends the current state, also when rejoining after a disconnect
+    fn join(&mut self, game_id: Uuid, token: Option<Uuid>) {
+        if let Some((joined_game, joined)) = self.seat {
+            return self.reject(None, ErrorCode::Unauthorized, format!("Connection already joined game {} as player {}!", joined_game, joined));
+        }
+        let (player_id, token) = match self.arc_lobby.lock().unwrap().join(game_id, token) {
+            Ok(seat) => seat,
+            Err(reason) => return self.reject(None, ErrorCode::Lobby, reason),
+        };
+        let seat = (game_id, player_id);
+        self.seat = Some(seat);
+        // the broadcast waits until the seat is registered and the stored state is sent, so no state is lost or sent twice
+        let mut connections = self.arc_connections.lock().unwrap();
+        for (s, uuid, _) in connections.iter_mut() {
+            if *uuid == self.connection_uuid {
+                *s = Some(seat);
+            }
+        }
+        println!("[LOG] Connection {} joined game {} as player {}", self.connection_uuid, game_id, player_id);
+        self.reply(&Outgoing::Joined{game_id: game_id, player_id: player_id, token: token});
+        if let Some(state) = self.last_states.lock().unwrap().get(&seat) {
+            push_outbox(&self.outbox, state.to_owned());
+        }
+    }
+
+    // serves the lobby requests right away, they do not go through a game
+    fn handle_lobby(&mut self, incoming: Incoming) {
+        match incoming {
+            Incoming::CreateGame(settings) => {
+                let result = self.arc_lobby.lock().unwrap().create(settings);
+                match result {
+                    Ok(game_id) => self.reply(&Outgoing::Created{game_id: game_id}),
+                    Err(reason) => self.reject(None, ErrorCode::Lobby, reason),
+                }
+            }
+            Incoming::ListGames => {
+                let games = self.arc_lobby.lock().unwrap().list();
+                self.reply(&Outgoing::Games(games));
+            }
+            Incoming::Join{game_id, token} => self.join(game_id, token),
+            Incoming::StartGame{game_id} => {
+                if self.seat.map(|(joined_game, _)| joined_game) != Some(game_id) {
+                    return self.reject(None, ErrorCode::Unauthorized, format!("Join game {} before starting it!", game_id));
+                }
+                let result = self.arc_lobby.lock().unwrap().start(game_id);
+                match result {
+                    Ok(()) => self.reply(&Outgoing::Started{game_id: game_id}),
+                    Err(reason) => self.reject(None, ErrorCode::Lobby, reason),
+                }
+            }
+            Incoming::Request(_) => (),
         }
     }
 
     fn handle_read(&mut self) -> Result<(), tungstenite::Error> {
         // receive new message
         let msg: Message;
-        // TODO add socket to player list
-        // TODO need option for state_machine to interrupt for broadcast to all players
         match self.socket.read_message() {
             Ok(val) => msg = val,
             Err(err) => {
@@ -98,25 +336,52 @@ impl Connection {
                 return Err(tungstenite::Error::ConnectionClosed);
             }
             Message::Text(json_msg) => {
-                println!("[DEBUG] Received message {}", json_msg);
-                let unpacked_cmd: CmdWrapper;
+                let request: Request;
                 match serde_json::from_str(&json_msg) {
-                    Ok(cmd) => unpacked_cmd = cmd,
+                    Ok(Incoming::Request(req)) => request = req,
+                    Ok(incoming) => {
+                        self.handle_lobby(incoming);
+                        return Ok(());
+                    }
                     Err(err) => {
-                        println!("[ERR] Failed to parse message, encountered error {}", err);
-                        self.socket.write_message(Message::Text(
-                            format!("{{ \"msg\": 'Invalid message! Could not parse command {} Encountered error: {}'}}", json_msg, err)
-                        )).unwrap();
+                        // the request id is recovered if at least the envelope is valid JSON
+                        let request_id = serde_json::from_str::<serde_json::Value>(&json_msg).ok()
+                            .and_then(|value| value.pointer("/Request/request_id").and_then(|id| id.as_u64()));
+                        let reason = format!("Invalid message! Could not parse command {} Encountered error: {}", json_msg, err);
+                        self.reject(request_id, ErrorCode::InvalidMessage, reason);
                         return Ok(());
                     }
                 }
+                // players may only send commands for their own seat
+                let game_id = match self.seat {
+                    None => {
+                        self.reject(Some(request.request_id), ErrorCode::Unauthorized, "Join a game before sending commands!".to_owned());
+                        return Ok(());
+                    }
+                    Some((_, player_id)) if player_id != request.command.player_id() => {
+                        let reason = format!("Player {} cannot send commands for player {}!", player_id, request.command.player_id());
+                        self.reject(Some(request.request_id), ErrorCode::Unauthorized, reason);
+                        return Ok(());
+                    }
+                    Some((game_id, _)) => game_id,
+                };
+                let arc_cmd_deque: CmdDeque = match self.arc_lobby.lock().unwrap().get_table(game_id) {
+                    Ok(table) if table.started => Arc::clone(&table.cmd_deque),
+                    Ok(_) => {
+                        self.reject(Some(request.request_id), ErrorCode::Lobby, format!("Game {} has not started yet!", game_id));
+                        return Ok(());
+                    }
+                    Err(reason) => {
+                        self.reject(Some(request.request_id), ErrorCode::Lobby, reason);
+                        return Ok(());
+                    }
+                };
 
-                println!("[LOG] Received and enqueue cmd {:?}", unpacked_cmd);
-                let (deque_lock, deque_cvar) = &*self.arc_cmd_deque;
+                println!("[LOG] Received and enqueue request {:?} for game {}", request, game_id);
+                let (deque_lock, deque_cvar) = &*arc_cmd_deque;
                 let mut cmd_deque = deque_lock.lock().unwrap();
-                cmd_deque.push_back(unpacked_cmd);
+                cmd_deque.push_back((self.connection_uuid, request));
                 deque_cvar.notify_one();
-                // TODO forward success/failure of cmd to client
             }
             Message::Binary(_) => (), // ignore Binary messages
             Message::Ping(_) => (), // ignore Ping messages
@@ -125,3 +390,28 @@ impl Connection {
         return Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn responses_carry_the_request_id_and_the_error_code() {
+        let ok = serde_json::to_value(Response::ok(3)).unwrap();
+        assert_eq!(ok, serde_json::json!({"request_id": 3, "result": {"Ok": null}}));
+        let err = serde_json::to_value(Response::err(None, ErrorCode::CannotExecute, "No".to_owned())).unwrap();
+        assert_eq!(err, serde_json::json!({"request_id": null, "result": {"Err": {"code": "CannotExecute", "reason": "No"}}}));
+    }
+
+    #[test]
+    fn requests_are_wrapped_with_their_id() {
+        let msg = r#"{"Request": {"request_id": 7, "command": {"Pass": {"player_id": 1}}}}"#;
+        match serde_json::from_str(msg).unwrap() {
+            Incoming::Request(request) => {
+                assert_eq!(request.request_id, 7);
+                assert_eq!(request.command.player_id(), 1);
+            }
+            incoming => panic!("Parsed {:?} instead of a request", incoming),
+        }
+    }
+}
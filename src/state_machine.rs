@@ -1,8 +1,13 @@
-use undo::{Command, Record, Chain};
+use undo::{Command, Record};
 
-use crate::player::ActionState;
-use crate::game_state::{GameState, Phase};
-use crate::card::{Card, CardType, Resource};
+use crate::player::{Player, ActionState, ACTIONS_PER_TURN, HEAT_PER_TEMPERATURE};
+use crate::game_state::{GameState, Phase, MAX_TEMPERATURE, TEMPERATURE_STEP, MAX_OXYGEN, MAX_OCEANS, MAX_VENUS, VENUS_STEP};
+use crate::card::{Card, CardType, Resource, TileType};
+use crate::colony::{Colonies, ColonyBonus, MAX_TRACK};
+use crate::effect::{Effect, Target, CardTarget, Multiplier, Condition, Scope, Placement, TilePlacement, GlobalParameter};
+use crate::event::Event;
+use crate::board;
+use crate::score;
 use crate::commands::*;
 
 
@@ -19,10 +24,11 @@ impl StateMachine {
         }
     }
 
-    fn lookup_card<'a>(&'a self, card_id: String) -> &'a Card {
+    // unknown ids come from clients, they must not end the game
+    fn lookup_card<'a>(&'a self, card_id: String) -> Result<&'a Card, CannotExecute> {
         match self.cards.iter().find(|c| c.id == card_id) {
-            Some(card) => card,
-            None => panic!(format!("Cannot find card {}", card_id)),
+            Some(card) => Ok(card),
+            None => Err(CannotExecute{reason: format!("Cannot find card {}!", card_id)}),
         }
     }
 
@@ -31,30 +37,661 @@ impl StateMachine {
     }
 
     pub fn apply(&mut self, command: CmdWrapper) -> undo::Result {
-        match command {
-            CmdWrapper::PlayCard(cmd) => self.play_card(cmd.owner_id, cmd.card_id.to_owned(), cmd),
-            CmdWrapper::ResearchCards(cmd) => self.research_card(cmd),
-            CmdWrapper::ChooseCorporation(cmd) => self.play_card(cmd.player_id, cmd.card_id.to_owned(), cmd),
+        let acting_player = match self.get_state().phase {
+            Phase::Action => command.acting_player(),
+            _ => None,
+        };
+        // The corporation's first action comes before anything else of the player, even before passing.
+        // The tiles of the last action have to be placed and pending cards discarded before the player does anything else.
+        if self.get_state().phase == Phase::Action {
+            let player_id = command.player_id();
+            let player = self.get_state().get_player(player_id)?;
+            match command {
+                // colonists discard as soon as they can, also outside of their turn
+                CmdWrapper::FirstAction(_) | CmdWrapper::DiscardCards(_) => (),
+                _ if player.pending_first_action => {
+                    return CannotExecute::new(format!("Player {} has to take the corporation's first action first!", player_id));
+                }
+                CmdWrapper::PlaceTile(_) => (),
+                _ if !player.pending_tiles.is_empty() => {
+                    return CannotExecute::new(format!("Player {} has to place their pending tiles first!", player_id));
+                }
+                _ if player.pending_discards > 0 => {
+                    return CannotExecute::new(format!("Player {} has to discard {} cards first!", player_id, player.pending_discards));
+                }
+                _ => (),
+            }
+        }
+        let chain = match command {
+            CmdWrapper::PlayCard(cmd) => {
+                self.play_card(cmd.owner_id, cmd.card_id.to_owned(), cmd.target_id, cmd.target_card.to_owned(), cmd.choice, cmd.colony, cmd)?
+            }
+            CmdWrapper::ResearchCards(cmd) => self.research_card(cmd)?,
+            CmdWrapper::ChooseCorporation(cmd) => self.play_card(cmd.player_id, cmd.card_id.to_owned(), None, None, None, None, cmd)?,
+            CmdWrapper::PlaceTile(cmd) => self.place_tile(cmd)?,
+            CmdWrapper::SellPatents(cmd) => Transaction::new().join(cmd),
+            CmdWrapper::PowerPlant(cmd) => self.power_plant(cmd)?,
+            CmdWrapper::Asteroid(cmd) => self.asteroid(cmd)?,
+            CmdWrapper::Aquifer(cmd) => self.aquifer(cmd)?,
+            CmdWrapper::Greenery(cmd) => self.greenery(cmd)?,
+            CmdWrapper::City(cmd) => self.city(cmd)?,
+            CmdWrapper::AirScrapping(cmd) => self.air_scrapping(cmd)?,
+            CmdWrapper::BuildColony(cmd) => self.build_colony(cmd)?,
+            CmdWrapper::Trade(cmd) => self.trade(cmd)?,
+            CmdWrapper::ConvertPlants(cmd) => self.convert_plants(cmd)?,
+            CmdWrapper::ConvertHeat(cmd) => self.convert_heat(cmd)?,
+            CmdWrapper::EndTurn(cmd) => Transaction::new().join(cmd),
+            CmdWrapper::Pass(cmd) => Transaction::new().join(cmd),
+            CmdWrapper::ClaimMilestone(cmd) => self.claim_milestone(cmd)?,
+            CmdWrapper::FundAward(cmd) => self.fund_award(cmd)?,
+            CmdWrapper::UseCardAction(cmd) => self.use_card_action(cmd)?,
+            CmdWrapper::ChoosePreludes(cmd) => Transaction::new().join(cmd),
+            CmdWrapper::FirstAction(cmd) => self.first_action(cmd)?,
+            CmdWrapper::DraftCard(cmd) => self.draft_card(cmd)?,
+            CmdWrapper::DiscardCards(cmd) => Transaction::new().join(cmd),
+        };
+        // every action uses up one of the player's actions in this turn
+        let chain = match acting_player {
+            Some(player_id) => chain.join(UseAction{player_id: player_id, turn: None}),
+            None => chain,
+        };
+        self.record.apply(chain)?;
+        // the generation ends once all players have passed
+        if self.get_state().phase == Phase::Action && self.get_state().players.iter().all(|p| p.action_state == ActionState::Passed) {
+            return self.production_phase();
         }
-        // TODO check if game is over
-        // TODO check for advance_phase
+        // the game ends once all players are done with their final greeneries
+        if self.get_state().phase == Phase::FinalGreenery && self.get_state().players.iter().all(|p| p.action_state == ActionState::Passed) {
+            return self.end_game();
+        }
+        // the action phase starts as soon as all players have chosen their cards
+        let phase = &self.get_state().phase;
+        if (*phase == Phase::Setup || *phase == Phase::Research) && self.check_cards_chosen().is_ok() {
+            if let Err(err) = self.transition_to_action() {
+                // e.g. a prelude cannot be played, the command that completed the choice is taken back
+                self.record.undo();
+                return Err(err);
+            }
+        }
+        Ok(())
     }
 
-    fn research_card(&mut self, command: ResearchCards) -> undo::Result {
+    fn research_card(&self, command: ResearchCards) -> Result<Transaction, CannotExecute> {
         let research_queue = &self.get_state().get_player(command.player_id)?.research_queue;
         let discard_ids = research_queue.iter().map(|c| c.id.to_owned()).filter(|id| !command.card_ids.contains(&id)).collect();
         let discard_cmd = DiscardResearch{player_id: command.player_id, card_ids: discard_ids};
-        let chain = Chain::new().join(command).join(discard_cmd);
-        self.record.apply(chain)
+        let chain = Transaction::new().join(command).join(discard_cmd);
+        Ok(chain)
+    }
+
+    // the draft hands are passed once the last player of the round has picked
+    fn draft_card(&self, command: DraftCard) -> Result<Transaction, CannotExecute> {
+        let state = self.get_state();
+        let remaining = state.get_player(command.player_id)?.draft_hand.len().saturating_sub(1);
+        let last_pick = state.players.iter()
+            .filter(|p| p.id != command.player_id)
+            .all(|p| p.draft_hand.len() == remaining);
+        let chain = Transaction::new().join(command);
+        match last_pick && remaining > 0 {
+            true => Ok(chain.join(PassDraft{forward: state.draft_forward()})),
+            false => Ok(chain),
+        }
+    }
+
+    fn place_tile(&self, command: PlaceTile) -> Result<Transaction, CannotExecute> {
+        let player = self.get_state().get_player(command.player_id)?;
+        let tile = player.pending_tiles.first().cloned();
+        let on_mars = !matches!(tile.as_ref().map(|t| &t.placement), Some(Placement::OffMars(_)));
+        let (rescs, cards) = match tile.as_ref().map(|t| &t.placement) {
+            Some(Placement::OffMars(_)) | None => (vec![], 0),
+            Some(_) => board::placement_bonus(&self.get_state().tiles, command.x, command.z),
+        };
+        let player_id = command.player_id;
+        // the placement is validated first, the bonus is only collected for a valid placement
+        let mut chain = Transaction::new()
+            .join(command)
+            .join(ModResources{player_id: player_id, rescs: rescs.to_owned()})
+            .join(DrawProjects{player_id: player_id, count: cards});
+        let mut levels = Levels::new(self.get_state());
+        if !rescs.is_empty() {
+            chain = self.join_event(chain, Event::PlacementBonus{player_id: player_id, resources: rescs}, None, &mut levels)?;
+        }
+        let tile_type = match tile {
+            Some(tile) => tile.tile_type,
+            None => return Ok(chain),
+        };
+        chain = match tile_type {
+            // the ocean was already counted when it was enqueued
+            TileType::Ocean => {
+                let chain = chain.join(RaiseParameter{player_id: player_id, parameter: GlobalParameter::Ocean, steps: 1});
+                self.join_event(chain, Event::ParameterRaised{player_id: player_id, parameter: GlobalParameter::Ocean}, None, &mut levels)?
+            }
+            TileType::Greenery => self.join_raise(chain, player_id, &GlobalParameter::Oxygen, 1, &mut levels)?,
+            _ => chain,
+        };
+        self.join_event(chain, Event::TilePlaced{player_id: player_id, tile_type: tile_type, on_mars: on_mars}, None, &mut levels)
+    }
+
+    fn fund_project(&self, player_id: usize, cost: u32, command: StandardProject, levels: &mut Levels) -> Result<Transaction, CannotExecute> {
+        let chain = Transaction::new()
+            .join(command)
+            .join(ModResources{player_id: player_id, rescs: vec![Resource::MegaCredits(-(cost as i32))]});
+        self.join_event(chain, Event::StandardProject{player_id: player_id, cost: cost}, None, levels)
+    }
+
+    fn power_plant(&self, command: StandardProject) -> Result<Transaction, CannotExecute> {
+        let player_id = command.player_id;
+        let mut levels = Levels::new(self.get_state());
+        let chain = self.fund_project(player_id, POWER_PLANT_COST, command, &mut levels)?;
+        self.join_production(chain, player_id, Resource::Energy(1), None, &mut levels)
+    }
+
+    fn asteroid(&self, command: StandardProject) -> Result<Transaction, CannotExecute> {
+        let player_id = command.player_id;
+        let mut levels = Levels::new(self.get_state());
+        let chain = self.fund_project(player_id, ASTEROID_COST, command, &mut levels)?;
+        self.join_raise(chain, player_id, &GlobalParameter::Temperature, 1, &mut levels)
+    }
+
+    fn air_scrapping(&self, command: StandardProject) -> Result<Transaction, CannotExecute> {
+        if !self.get_state().use_venus {
+            return Err(CannotExecute{reason: "Air scrapping is only available with Venus Next!".to_owned()});
+        }
+        let player_id = command.player_id;
+        let mut levels = Levels::new(self.get_state());
+        let chain = self.fund_project(player_id, AIR_SCRAPPING_COST, command, &mut levels)?;
+        self.join_raise(chain, player_id, &GlobalParameter::Venus, 1, &mut levels)
+    }
+
+    fn aquifer(&self, command: StandardProject) -> Result<Transaction, CannotExecute> {
+        let player_id = command.player_id;
+        let mut levels = Levels::new(self.get_state());
+        if levels.oceans >= MAX_OCEANS {
+            return Err(CannotExecute{reason: "Cannot fund aquifer, all oceans are placed!".to_owned()});
+        }
+        let chain = self.fund_project(player_id, AQUIFER_COST, command, &mut levels)?;
+        let chain = join_ocean(chain, player_id, TilePlacement{tile_type: TileType::Ocean, placement: Placement::Normal}, &mut levels);
+        Ok(chain)
+    }
+
+    fn greenery(&self, command: StandardProject) -> Result<Transaction, CannotExecute> {
+        let player_id = command.player_id;
+        let chain = self.fund_project(player_id, GREENERY_COST, command, &mut Levels::new(self.get_state()))?
+            .join(QueueTile{player_id: player_id, tile: TilePlacement{tile_type: TileType::Greenery, placement: Placement::Normal}});
+        Ok(chain)
+    }
+
+    fn city(&self, command: StandardProject) -> Result<Transaction, CannotExecute> {
+        let player_id = command.player_id;
+        let mut levels = Levels::new(self.get_state());
+        let chain = self.fund_project(player_id, CITY_COST, command, &mut levels)?
+            .join(QueueTile{player_id: player_id, tile: TilePlacement{tile_type: TileType::City, placement: Placement::Normal}});
+        self.join_production(chain, player_id, Resource::MegaCredits(1), None, &mut levels)
+    }
+
+    fn build_colony(&self, command: BuildColony) -> Result<Transaction, CannotExecute> {
+        let player_id = command.player_id;
+        let colony = command.colony;
+        let target_card = command.target_card.to_owned();
+        let chain = Transaction::new()
+            .join(command)
+            .join(ModResources{player_id: player_id, rescs: vec![Resource::MegaCredits(-(BUILD_COLONY_COST as i32))]});
+        let mut levels = Levels::new(self.get_state());
+        let chain = self.join_event(chain, Event::StandardProject{player_id: player_id, cost: BUILD_COLONY_COST}, None, &mut levels)?;
+        self.join_colony(chain, player_id, colony, false, target_card, &mut levels)
+    }
+
+    // The trader collects the income of the track position, raised by Trade Envoys and the like.
+    // Afterwards every colonist on the tile gets the colony bonus once for each of their colonies.
+    fn trade(&self, command: Trade) -> Result<Transaction, CannotExecute> {
+        let state = self.get_state();
+        let player_id = command.player_id;
+        let tile = match state.colonies.iter().find(|c| c.name == command.colony) {
+            Some(tile) => tile,
+            None => return Err(CannotExecute{reason: format!("Colony {:?} is not part of this game!", command.colony)}),
+        };
+        let cost = command.payment.resource();
+        let cost = cost.with_count(cost.count().saturating_sub(state.trade_discount(player_id) as i32).max(0));
+        let income = tile.name.trade_income((tile.track + state.trade_offset(player_id)).min(MAX_TRACK));
+        let bonus = tile.name.colony_bonus();
+        let colonists = tile.colonies.to_owned();
+        let target_card = command.target_card.to_owned();
+        let mut levels = Levels::new(state);
+        let chain = Transaction::new()
+            .join(command)
+            .join(ModResources{player_id: player_id, rescs: vec![cost.times(-1)]});
+        let mut chain = self.join_colony_bonus(chain, player_id, &income, target_card, &mut levels)?;
+        for colonist in colonists {
+            chain = self.join_colony_bonus(chain, colonist, &bonus, None, &mut levels)?;
+        }
+        Ok(chain)
+    }
+
+    fn claim_milestone(&self, command: ClaimMilestone) -> Result<Transaction, CannotExecute> {
+        let player_id = command.player_id;
+        let chain = Transaction::new()
+            .join(command)
+            .join(ModResources{player_id: player_id, rescs: vec![Resource::MegaCredits(-(MILESTONE_COST as i32))]});
+        Ok(chain)
+    }
+
+    // each funded award makes the next one more expensive
+    fn fund_award(&self, command: FundAward) -> Result<Transaction, CannotExecute> {
+        let player_id = command.player_id;
+        let funded = self.get_state().awards.iter().filter(|a| a.owner.is_some()).count();
+        // the command itself rejects a fourth award
+        let cost = AWARD_COSTS.get(funded).copied().unwrap_or(0);
+        let chain = Transaction::new()
+            .join(command)
+            .join(ModResources{player_id: player_id, rescs: vec![Resource::MegaCredits(-(cost as i32))]});
+        Ok(chain)
+    }
+
+    fn convert_plants(&self, command: ConvertPlants) -> Result<Transaction, CannotExecute> {
+        let player_id = command.player_id;
+        let plants = self.get_state().get_player(player_id)?.plants_per_greenery();
+        let chain = Transaction::new()
+            .join(command)
+            .join(ModResources{player_id: player_id, rescs: vec![Resource::Plants(-(plants as i32))]})
+            .join(QueueTile{player_id: player_id, tile: TilePlacement{tile_type: TileType::Greenery, placement: Placement::Normal}});
+        Ok(chain)
+    }
+
+    fn convert_heat(&self, command: ConvertHeat) -> Result<Transaction, CannotExecute> {
+        let player_id = command.player_id;
+        let chain = Transaction::new()
+            .join(command)
+            .join(ModResources{player_id: player_id, rescs: vec![Resource::Heat(-(HEAT_PER_TEMPERATURE as i32))]});
+        self.join_raise(chain, player_id, &GlobalParameter::Temperature, 1, &mut Levels::new(self.get_state()))
+    }
+
+    fn play_card(
+            &self,
+            player_id: usize,
+            card_id: String,
+            target_id: Option<usize>,
+            target_card: Option<String>,
+            choice: Option<usize>,
+            colony: Option<Colonies>,
+            command: impl Command<GameState> + 'static
+    ) -> Result<Transaction, CannotExecute> {
+        let card = self.lookup_card(card_id)?.to_owned();
+        let mut context = PlayContext {
+            card: &card,
+            player_id: player_id,
+            target_id: target_id,
+            target_card: target_card,
+            choice: choice,
+            colony: colony,
+            payment: None,
+            levels: Levels::new(self.get_state()),
+        };
+        self.check_effects(&card.effects, &context)?;
+        let chain = Transaction::new().join(command);
+        let chain = self.join_effects(chain, &card.effects, &mut context)?;
+        let event = Event::CardPlayed{player_id: player_id, tags: card.tags.to_owned(), card_type: card.card_type.to_owned(), cost: card.cost};
+        self.join_event(chain, event, Some((player_id, &card)), &mut context.levels)
+    }
+
+    fn use_card_action(&self, command: UseCardAction) -> Result<Transaction, CannotExecute> {
+        let card = self.lookup_card(command.card_id.to_owned())?.to_owned();
+        let mut context = PlayContext {
+            card: &card,
+            player_id: command.player_id,
+            target_id: command.target_id,
+            target_card: command.target_card.to_owned(),
+            choice: command.choice,
+            colony: None,
+            payment: command.payment.to_owned(),
+            levels: Levels::new(self.get_state()),
+        };
+        self.check_effects(&card.action, &context)?;
+        let chain = Transaction::new().join(command);
+        self.join_effects(chain, &card.action, &mut context)
+    }
+
+    fn first_action(&self, command: FirstAction) -> Result<Transaction, CannotExecute> {
+        let card = match &self.get_state().get_player(command.player_id)?.corporation {
+            Some(card) => card.to_owned(),
+            None => return Err(CannotExecute{reason: format!("Player {} has no corporation!", command.player_id)}),
+        };
+        let mut context = PlayContext {
+            card: &card,
+            player_id: command.player_id,
+            target_id: None,
+            target_card: None,
+            choice: None,
+            colony: command.colony,
+            payment: None,
+            levels: Levels::new(self.get_state()),
+        };
+        let chain = Transaction::new().join(command);
+        self.join_effects(chain, &card.first_action, &mut context)
+    }
+
+    // Effects the player cannot afford are rejected before the card is paid for or its action is used.
+    // Anything else failing later on is undone by the transaction.
+    fn check_effects(&self, effects: &Vec<Effect>, context: &PlayContext) -> Result<(), CannotExecute> {
+        let player = self.get_state().get_player(context.player_id)?;
+        for effect in effects.iter() {
+            match effect {
+                Effect::GainResource{resource, target: Target::Own, multiplier} => {
+                    let resource = resource.times(self.multiply(multiplier, context));
+                    let available = player.inventory_count(&resource);
+                    if resource.count() < 0 && available < resource.count().unsigned_abs() {
+                        return Err(CannotExecute{reason: format!("Card {} needs {} but player {} has only {}!", context.card.id, resource.times(-1), player.id, available)});
+                    }
+                }
+                // mandatory production decreases, own or of the target players, must be possible
+                Effect::ModProduction{resource, target, multiplier} => {
+                    let resource = resource.times(self.multiply(multiplier, context));
+                    if resource.count() >= 0 {
+                        continue;
+                    }
+                    for target_id in context.resolve_targets(target, false, self.get_state())? {
+                        let production = self.get_state().get_player(target_id)?.production_count(&resource);
+                        if production + resource.count() < Player::min_production(&resource) {
+                            return Err(CannotExecute{reason: format!("Card {} decreases the production by {} but player {} produces only {}!", context.card.id, resource.times(-1), target_id, production)});
+                        }
+                    }
+                }
+                Effect::CardResource{resource, count, target} if *count < 0 => {
+                    let card_id = context.resolve_card(target, self.get_state())?;
+                    // the played card itself is not in play yet and holds nothing
+                    let available = self.get_state().cards_in_play.iter().find(|c| c.card.id == card_id).map_or(0, |c| c.resources);
+                    if available < count.unsigned_abs() {
+                        return Err(CannotExecute{reason: format!("Card {} needs {} {:?} on card {}, it holds only {}!", context.card.id, -count, resource, card_id, available)});
+                    }
+                }
+                Effect::Pay(cost) => {
+                    check_payment_of(context.card, *cost, &context.pay(*cost), player.id, self.get_state())
+                        .map_err(|err| CannotExecute{reason: err.to_string()})?;
+                }
+                Effect::Choice(options) => {
+                    if let Some(option) = context.choice.and_then(|idx| options.get(idx)) {
+                        self.check_effects(option, context)?;
+                    }
+                }
+                Effect::Conditional{condition, then, otherwise} => {
+                    let branch = if self.check_condition(condition, context) { then } else { otherwise };
+                    self.check_effects(branch, context)?;
+                }
+                _ => (),
+            }
+        }
+        Ok(())
     }
 
-    fn play_card(&mut self, player_id: usize, card_id: String, command: impl Command<GameState> + 'static) -> undo::Result {
-        let card = self.lookup_card(card_id);
-        let rescs_cmd = ModResources{player_id: player_id, rescs: card.resources.to_owned()};
-        let prod_cmd = ModProduction{player_id: player_id, rescs: card.production.to_owned()};
-        let chain = Chain::new().join(command).join(rescs_cmd).join(prod_cmd);
-        // TODO one-time Actions/effects
-        self.record.apply(chain)
+    fn join_effects(&self, mut chain: Transaction, effects: &Vec<Effect>, context: &mut PlayContext) -> Result<Transaction, CannotExecute> {
+        let player_id = context.player_id;
+        for effect in effects.iter() {
+            chain = match effect {
+                Effect::GainResource{resource, target, multiplier} => {
+                    let resource = resource.times(self.multiply(multiplier, context));
+                    let optional = *target != Target::Own && resource.count() < 0;
+                    for target_id in context.resolve_targets(target, optional, self.get_state())? {
+                        let resource = match optional {
+                            true => self.removable(target_id, &resource)?,
+                            false => resource.to_owned(),
+                        };
+                        chain = chain.join(ModResources{player_id: target_id, rescs: vec![resource]});
+                    }
+                    chain
+                }
+                Effect::Steal{resource, target} => {
+                    for target_id in context.resolve_targets(target, true, self.get_state())? {
+                        let removed = self.removable(target_id, &resource.times(-1))?;
+                        chain = chain
+                            .join(ModResources{player_id: target_id, rescs: vec![removed.to_owned()]})
+                            .join(ModResources{player_id: player_id, rescs: vec![removed.times(-1)]});
+                    }
+                    chain
+                }
+                Effect::ModProduction{resource, target, multiplier} => {
+                    let resource = resource.times(self.multiply(multiplier, context));
+                    for target_id in context.resolve_targets(target, false, self.get_state())? {
+                        chain = self.join_production(chain, target_id, resource.to_owned(), Some((player_id, context.card)), &mut context.levels)?;
+                    }
+                    chain
+                }
+                Effect::ModRating{steps, multiplier} => {
+                    chain.join(ModRating{player_id: player_id, steps: steps * self.multiply(multiplier, context)})
+                }
+                Effect::DrawCards(count) => chain.join(DrawProjects{player_id: player_id, count: *count as usize}),
+                Effect::Pay(cost) => {
+                    let payment = context.pay(*cost);
+                    let rescs = vec![
+                        Resource::MegaCredits(-(payment.megacredits as i32)),
+                        Resource::Steel(-(payment.steel as i32)),
+                        Resource::Titanium(-(payment.titanium as i32)),
+                    ];
+                    chain.join(ModResources{player_id: player_id, rescs: rescs})
+                }
+                Effect::CardResource{resource, count, target} => {
+                    chain.join(ModCardResources{card_id: context.resolve_card(target, self.get_state())?, resource: resource.to_owned(), count: *count})
+                }
+                Effect::Choice(options) => {
+                    let option = match context.choice.and_then(|idx| options.get(idx)) {
+                        Some(option) => option,
+                        None => return Err(CannotExecute{reason: format!("Card {} requires a choice out of {} options!", context.card.id, options.len())}),
+                    };
+                    self.join_effects(chain, option, context)?
+                }
+                Effect::Conditional{condition, then, otherwise} => {
+                    let branch = if self.check_condition(condition, context) { then } else { otherwise };
+                    self.join_effects(chain, branch, context)?
+                }
+                Effect::PlaceTile(tile) => {
+                    if tile.tile_type == TileType::Ocean {
+                        join_ocean(chain, player_id, tile.to_owned(), &mut context.levels)
+                    } else {
+                        chain.join(QueueTile{player_id: player_id, tile: tile.to_owned()})
+                    }
+                }
+                Effect::RaiseParameter{parameter, steps} => self.join_raise(chain, player_id, parameter, *steps, &mut context.levels)?,
+                Effect::PlaceColony{duplicate} => {
+                    let colony = match context.colony {
+                        Some(colony) => colony,
+                        None => return Err(CannotExecute{reason: format!("Card {} requires a colony tile!", context.card.id)}),
+                    };
+                    let target_card = context.target_card.to_owned();
+                    self.join_colony(chain, player_id, colony, *duplicate, target_card, &mut context.levels)?
+                }
+            };
+        }
+        Ok(chain)
+    }
+
+    // Raises a global parameter step by step and collects the bonuses printed on the board:
+    // heat production at -24°C and -20°C, an ocean at 0°C and a temperature step at 8% oxygen.
+    // On the venus track a card is drawn at 8% and the TR is raised once more at 16%.
+    // Steps beyond the maximum are dropped and do not raise the TR.
+    fn join_raise(
+            &self,
+            mut chain: Transaction,
+            player_id: usize,
+            parameter: &GlobalParameter,
+            steps: u32,
+            levels: &mut Levels
+    ) -> Result<Transaction, CannotExecute> {
+        for _ in 0..steps {
+            match parameter {
+                GlobalParameter::Temperature => {
+                    if levels.temperature >= MAX_TEMPERATURE {
+                        break;
+                    }
+                    levels.temperature += TEMPERATURE_STEP;
+                    chain = chain.join(RaiseParameter{player_id: player_id, parameter: GlobalParameter::Temperature, steps: 1});
+                    chain = self.join_event(chain, Event::ParameterRaised{player_id: player_id, parameter: GlobalParameter::Temperature}, None, levels)?;
+                    chain = match levels.temperature {
+                        -24 | -20 => self.join_production(chain, player_id, Resource::Heat(1), None, levels)?,
+                        0 => join_ocean(chain, player_id, TilePlacement{tile_type: TileType::Ocean, placement: Placement::Normal}, levels),
+                        _ => chain,
+                    };
+                }
+                GlobalParameter::Oxygen => {
+                    if levels.oxygen >= MAX_OXYGEN {
+                        break;
+                    }
+                    levels.oxygen += 1;
+                    chain = chain.join(RaiseParameter{player_id: player_id, parameter: GlobalParameter::Oxygen, steps: 1});
+                    chain = self.join_event(chain, Event::ParameterRaised{player_id: player_id, parameter: GlobalParameter::Oxygen}, None, levels)?;
+                    if levels.oxygen == 8 {
+                        chain = self.join_raise(chain, player_id, &GlobalParameter::Temperature, 1, levels)?;
+                    }
+                }
+                GlobalParameter::Ocean => {
+                    if levels.oceans >= MAX_OCEANS {
+                        break;
+                    }
+                    levels.oceans += 1;
+                    chain = chain.join(RaiseParameter{player_id: player_id, parameter: GlobalParameter::Ocean, steps: 1});
+                    chain = self.join_event(chain, Event::ParameterRaised{player_id: player_id, parameter: GlobalParameter::Ocean}, None, levels)?;
+                }
+                GlobalParameter::Venus => {
+                    if levels.venus >= MAX_VENUS {
+                        break;
+                    }
+                    levels.venus += VENUS_STEP;
+                    chain = chain.join(RaiseParameter{player_id: player_id, parameter: GlobalParameter::Venus, steps: 1});
+                    chain = self.join_event(chain, Event::ParameterRaised{player_id: player_id, parameter: GlobalParameter::Venus}, None, levels)?;
+                    chain = match levels.venus {
+                        8 => chain.join(DrawProjects{player_id: player_id, count: 1}),
+                        16 => chain.join(ModRating{player_id: player_id, steps: 1}),
+                        _ => chain,
+                    };
+                }
+            }
+        }
+        Ok(chain)
+    }
+
+    // places the colony and hands out the placement bonus to the colonist
+    fn join_colony(
+            &self,
+            chain: Transaction,
+            player_id: usize,
+            colony: Colonies,
+            duplicate: bool,
+            target_card: Option<String>,
+            levels: &mut Levels
+    ) -> Result<Transaction, CannotExecute> {
+        let chain = chain.join(PlaceColony{player_id: player_id, colony: colony, duplicate: duplicate, track: None});
+        let chain = self.join_colony_bonus(chain, player_id, &colony.placement_bonus(), target_card, levels)?;
+        self.join_event(chain, Event::ColonyPlaced{player_id: player_id}, None, levels)
+    }
+
+    // Without a target card, animals, microbes and floaters go to the player's first card that holds them.
+    // They are lost if the player has no such card.
+    fn join_colony_bonus(
+            &self,
+            chain: Transaction,
+            player_id: usize,
+            bonus: &ColonyBonus,
+            target_card: Option<String>,
+            levels: &mut Levels
+    ) -> Result<Transaction, CannotExecute> {
+        let chain = match bonus {
+            ColonyBonus::Resource(resource) => chain.join(ModResources{player_id: player_id, rescs: vec![resource.to_owned()]}),
+            ColonyBonus::Production(resource) => self.join_production(chain, player_id, resource.to_owned(), None, levels)?,
+            ColonyBonus::Cards(count) => chain.join(DrawProjects{player_id: player_id, count: *count as usize}),
+            ColonyBonus::DrawDiscard(count) => chain
+                .join(DrawProjects{player_id: player_id, count: *count as usize})
+                .join(QueueDiscard{player_id: player_id, count: *count}),
+            ColonyBonus::CardResource(resource, count) => {
+                let card_id = target_card.or_else(|| self.get_state().cards_in_play.iter()
+                    .find(|c| c.owner == player_id && c.card.interactions.holds_resources == *resource)
+                    .map(|c| c.card.id.to_owned()));
+                match card_id {
+                    Some(card_id) if *count > 0 => chain.join(ModCardResources{card_id: card_id, resource: resource.to_owned(), count: *count as i32}),
+                    _ => chain,
+                }
+            }
+            ColonyBonus::Ocean => join_ocean(chain, player_id, TilePlacement{tile_type: TileType::Ocean, placement: Placement::Normal}, levels),
+        };
+        Ok(chain)
+    }
+
+    // a player cannot lose more resources than they have
+    fn removable(&self, player_id: usize, resource: &Resource) -> Result<Resource, CannotExecute> {
+        let available = self.get_state().get_player(player_id)?.inventory_count(resource) as i32;
+        Ok(resource.with_count(resource.count().max(-available)))
+    }
+
+    fn join_production(
+            &self,
+            chain: Transaction,
+            player_id: usize,
+            resource: Resource,
+            pending: Option<(usize, &Card)>,
+            levels: &mut Levels
+    ) -> Result<Transaction, CannotExecute> {
+        let event = Event::ProductionChanged{player_id: player_id, resource: resource.to_owned()};
+        let chain = chain.join(ModProduction{player_id: player_id, rescs: vec![resource]});
+        self.join_event(chain, event, pending, levels)
+    }
+
+    // Joins the effects of all triggers reacting to the event, they always benefit the owner of the triggering card.
+    // A card that is being played is not in play yet, it is passed with its owner as `pending` for effects like "including this".
+    fn join_event(
+            &self,
+            mut chain: Transaction,
+            event: Event,
+            pending: Option<(usize, &Card)>,
+            levels: &mut Levels
+    ) -> Result<Transaction, CannotExecute> {
+        let state = self.get_state();
+        let mut listeners: Vec<(usize, &Card)> = state.cards_in_play.iter().map(|c| (c.owner, &c.card)).collect();
+        listeners.extend(state.players.iter().filter_map(|p| p.corporation.as_ref().map(|c| (p.id, c))));
+        if let Some((owner, card)) = pending {
+            if !listeners.iter().any(|(_, c)| c.id == card.id) {
+                listeners.push((owner, card));
+            }
+        }
+        for (owner, card) in listeners {
+            for trigger in card.triggers.iter() {
+                for _ in 0..trigger.fires(&event, owner) {
+                    // the player of the event is the target of the trigger's effects on other players
+                    let mut context = PlayContext {
+                        card: card,
+                        player_id: owner,
+                        target_id: Some(event.player_id()),
+                        target_card: None,
+                        choice: None,
+                        colony: None,
+                        payment: None,
+                        levels: levels.clone(),
+                    };
+                    chain = self.join_effects(chain, &trigger.effects, &mut context)?;
+                    *levels = context.levels;
+                }
+            }
+        }
+        Ok(chain)
+    }
+
+    // the played card is not yet in play, so its own tags are added to the count
+    fn multiply(&self, multiplier: &Multiplier, context: &PlayContext) -> i32 {
+        match multiplier {
+            Multiplier::Once => 1,
+            Multiplier::PerTag{tag, per, scope} => {
+                let mut count = self.get_state().count_tags(context.player_id, tag, scope);
+                if *scope != Scope::Opponents {
+                    count += context.card.tags.iter().filter(|t| *t == tag).count();
+                }
+                (count / *per as usize) as i32
+            }
+            Multiplier::PerTile(tile_type) => self.get_state().count_tiles(tile_type) as i32,
+        }
+    }
+
+    fn check_condition(&self, condition: &Condition, context: &PlayContext) -> bool {
+        match condition {
+            Condition::HasTags{tag, count} => {
+                let owned = self.get_state().count_tags(context.player_id, tag, &Scope::Own);
+                owned + context.card.tags.iter().filter(|t| *t == tag).count() >= *count as usize
+            }
+        }
     }
 
     pub fn advance_phase(&mut self) -> undo::Result {
@@ -65,17 +702,25 @@ impl StateMachine {
             Phase::Research => self.transition_to_action(),
             Phase::Action => self.production_phase(),
             Phase::Production => self.research_phase(),
+            Phase::FinalGreenery => self.end_game(),
+            Phase::End => CannotExecute::new("Cannot advance phase, the game is over!".to_owned()),
         }
     }
 
     fn setup_phase(&mut self) -> undo::Result {
         let player_ids = self.get_state().players.iter().map(|p| p.id).collect::<Vec<usize>>();
-        let mut chain = Chain::new();
+        let mut chain = Transaction::new();
         for id in player_ids {
             // assign corporations
             chain = chain.join(DrawCards{player_id: id, count: 2, card_type: CardType::Corporation});
             // assign start cards
-            chain = chain.join(DrawCards{player_id: id, count: 10, card_type: CardType::Project});
+            chain = match self.get_state().drafting {
+                true => chain.join(DealDraft{player_id: id, count: 10}),
+                false => chain.join(DrawCards{player_id: id, count: 10, card_type: CardType::Project}),
+            };
+            if self.get_state().use_preludes {
+                chain = chain.join(DrawCards{player_id: id, count: 4, card_type: CardType::Prelude});
+            }
         }
         match self.record.apply(chain) {
             Ok(()) => self.record.as_mut_target().phase = Phase::Setup,
@@ -84,31 +729,66 @@ impl StateMachine {
         Ok(())
     }
 
-    fn transition_to_action(&mut self) -> undo::Result {
+    // the corporations, preludes, drafted and researched cards of all players are chosen
+    fn check_cards_chosen(&self) -> undo::Result {
         // all players have to choose a corporation
         if self.get_state().players.iter().any(|p| p.corporation.is_none()) {
             return CannotExecute::new("Cannot advance to Action phase, a player has not selected a corporation!".to_owned());
         }
-        // players may hold only projects, no corporations
+        // players may hold only projects, no corporations or preludes
         if self.get_state().players.iter().flat_map(|p| &p.hand).any(|card| card.card_type == CardType::Corporation) {
             return CannotExecute::new("Cannot advance to Action phase, a player has a corporation card in hand!".to_owned());
         }
+        if self.get_state().players.iter().flat_map(|p| &p.hand).any(|card| card.card_type == CardType::Prelude) {
+            return CannotExecute::new("Cannot advance to Action phase, a player has a prelude card in hand!".to_owned());
+        }
+        if self.get_state().players.iter().any(|p| !p.draft_hand.is_empty()) {
+            return CannotExecute::new("Cannot advance to Action phase, the draft is not finished!".to_owned());
+        }
         // all players have to empty their research queue
         if !self.get_state().players.iter().all(|p| p.research_queue.is_empty()) {
             return CannotExecute::new("Cannot advance to Action phase, a player still has research enqueued!".to_owned());
         }
+        Ok(())
+    }
+
+    fn transition_to_action(&mut self) -> undo::Result {
+        self.check_cards_chosen()?;
+        if self.get_state().phase == Phase::Setup {
+            self.play_preludes()?;
+        }
         self.record.as_mut_target().phase = Phase::Action;
+        for player in self.record.as_mut_target().players.iter_mut() {
+            player.action_state = ActionState::Waiting;
+        }
         let start_player_id = self.get_state().start_player;
-        self.record.as_mut_target().players[start_player_id].action_state = ActionState::Acting(2);
+        self.record.as_mut_target().players[start_player_id].action_state = ActionState::Acting(ACTIONS_PER_TURN);
         self.record.as_mut_target().active_player = start_player_id;
         Ok(())
     }
 
+    // the kept preludes are played right after the corporations, before the first generation starts
+    fn play_preludes(&mut self) -> undo::Result {
+        let preludes: Vec<(usize, String)> = self.get_state().players.iter()
+            .flat_map(|p| p.preludes.iter().map(move |c| (p.id, c.id.to_owned())))
+            .collect();
+        // all preludes are played at once, if one fails none of them is played
+        let mut transaction = Transaction::new();
+        for (player_id, card_id) in preludes {
+            let command = PlayPrelude{player_id: player_id, card_id: card_id.to_owned()};
+            transaction = transaction.join(self.play_card(player_id, card_id, None, None, None, None, command)?);
+        }
+        self.record.apply(transaction)
+    }
+
     fn research_phase(&mut self) -> undo::Result {
         let player_ids = self.get_state().players.iter().map(|p| p.id).collect::<Vec<usize>>();
-        let mut chain = Chain::new();
+        let mut chain = Transaction::new();
         for id in player_ids {
-            chain = chain.join(DrawCards{player_id: id, count: 4, card_type: CardType::Project});
+            chain = match self.get_state().drafting {
+                true => chain.join(DealDraft{player_id: id, count: 4}),
+                false => chain.join(DrawCards{player_id: id, count: 4, card_type: CardType::Project}),
+            };
         }
         match self.record.apply(chain) {
             Ok(()) => self.record.as_mut_target().phase = Phase::Research,
@@ -118,7 +798,7 @@ impl StateMachine {
     }
 
     fn production_phase(&mut self) -> undo::Result {
-        let mut chain = Chain::new();
+        let mut chain = Transaction::new();
         for player in self.get_state().players.iter() {
             let rescs = vec![
                 Resource::MegaCredits(player.production.megacredits + player.tf_rating),
@@ -132,16 +812,1329 @@ impl StateMachine {
         }
         match self.record.apply(chain) {
             Ok(()) => {
-                // TODO
-                // reset marker on action cards
+                // blue card actions can be used again in the next generation
+                for owned_card in self.record.as_mut_target().cards_in_play.iter_mut() {
+                    owned_card.used = false;
+                }
+                // trade fleets return and the markers of the active colony tiles move up one step
+                let active: Vec<bool> = self.get_state().colonies.iter().map(|c| c.is_active(self.get_state())).collect();
+                for (tile, active) in self.record.as_mut_target().colonies.iter_mut().zip(active) {
+                    tile.trader = None;
+                    if active {
+                        tile.track = (tile.track + 1).min(MAX_TRACK);
+                    }
+                }
                 self.record.as_mut_target().generation += 1;
                 // wrapping increment start_player
                 let old_start_player = self.get_state().start_player;
                 self.record.as_mut_target().start_player = (old_start_player + 1) % self.get_state().players.len();
+                // the game ends after the generation in which all global parameters are maxed,
+                // a solo game also ends after its last generation
+                let last_generation = self.get_state().generation_limit().is_some_and(|limit| self.get_state().generation >= limit);
+                if self.get_state().is_terraformed() || last_generation {
+                    // every player passes once they are done with their final greeneries
+                    self.record.as_mut_target().phase = Phase::FinalGreenery;
+                    for player in self.record.as_mut_target().players.iter_mut() {
+                        player.action_state = ActionState::Waiting;
+                    }
+                    return Ok(());
+                }
                 // transition to research phase
                 self.research_phase()
             }
             Err(err) => Err(err),
         }
     }
+
+    fn end_game(&mut self) -> undo::Result {
+        // all greeneries of the final round have to be placed
+        if self.get_state().players.iter().any(|p| !p.pending_tiles.is_empty()) {
+            return CannotExecute::new("Cannot end the game, a player still has tiles to place!".to_owned());
+        }
+        let final_scores = score::final_scores(self.get_state());
+        let game_state = self.record.as_mut_target();
+        game_state.final_scores = final_scores;
+        if game_state.solo {
+            game_state.solo_won = Some(game_state.is_terraformed());
+        }
+        game_state.phase = Phase::End;
+        Ok(())
+    }
+}
+
+// Global parameters while a chain is built, so that later effects see the steps raised before.
+// Oceans count as soon as they are enqueued, so no more than 9 oceans are ever handed out.
+#[derive(Clone)]
+struct Levels {
+    temperature: i32,
+    oxygen: u32,
+    oceans: u32,
+    venus: u32,
+}
+
+impl Levels {
+    fn new(game_state: &GameState) -> Levels {
+        let pending_oceans = game_state.players.iter()
+            .flat_map(|p| &p.pending_tiles)
+            .filter(|t| t.tile_type == TileType::Ocean)
+            .count() as u32;
+        Levels {
+            temperature: game_state.temperature,
+            oxygen: game_state.oxygen,
+            oceans: game_state.oceans_placed + pending_oceans,
+            venus: game_state.venus,
+        }
+    }
+}
+
+struct PlayContext<'a> {
+    card: &'a Card,
+    player_id: usize,
+    target_id: Option<usize>,
+    target_card: Option<String>,
+    choice: Option<usize>,
+    colony: Option<Colonies>,
+    payment: Option<Payment>,
+    levels: Levels,
+}
+
+impl<'a> PlayContext<'a> {
+    fn pay(&self, cost: u32) -> Payment {
+        self.payment.to_owned().unwrap_or(Payment{megacredits: cost, steel: 0, titanium: 0})
+    }
+
+    // Optional effects, i.e. removing resources, are skipped without a target.
+    // Mandatory ones, i.e. decreasing production, require a valid target.
+    fn resolve_targets(&self, target: &Target, optional: bool, game_state: &GameState) -> Result<Vec<usize>, CannotExecute> {
+        let target_id = match target {
+            Target::Own => return Ok(vec![self.player_id]),
+            Target::AllOpponents => return Ok(game_state.players.iter().map(|p| p.id).filter(|id| *id != self.player_id).collect()),
+            Target::AnyPlayer | Target::Opponent | Target::EventPlayer => match self.target_id {
+                Some(id) => id,
+                None if optional => return Ok(vec![]),
+                None => return Err(CannotExecute{reason: format!("Card {} requires a target player!", self.card.id)}),
+            },
+        };
+        game_state.get_player(target_id)?;
+        if *target == Target::Opponent && target_id == self.player_id {
+            return Err(CannotExecute{reason: format!("Card {} must target an opponent!", self.card.id)});
+        }
+        Ok(vec![target_id])
+    }
+
+    fn resolve_card(&self, target: &CardTarget, game_state: &GameState) -> Result<String, CannotExecute> {
+        match (target, &self.target_card) {
+            (CardTarget::This, _) => Ok(self.card.id.to_owned()),
+            (CardTarget::Other, Some(card_id)) if *card_id == self.card.id => {
+                Err(CannotExecute{reason: format!("Card {} requires another card as target!", self.card.id)})
+            }
+            (CardTarget::Tagged(tag), Some(card_id)) => {
+                let tagged = match card_id == &self.card.id {
+                    true => self.card.tags.contains(tag),
+                    false => game_state.cards_in_play.iter().any(|c| c.card.id == *card_id && c.card.tags.contains(tag)),
+                };
+                match tagged {
+                    true => Ok(card_id.to_owned()),
+                    false => Err(CannotExecute{reason: format!("Card {} requires a target card with a {:?} tag!", self.card.id, tag)}),
+                }
+            }
+            (_, Some(card_id)) => Ok(card_id.to_owned()),
+            (_, None) => Err(CannotExecute{reason: format!("Card {} requires a target card!", self.card.id)}),
+        }
+    }
+}
+
+// oceans beyond the maximum are not placed at all
+fn join_ocean(chain: Transaction, player_id: usize, tile: TilePlacement, levels: &mut Levels) -> Transaction {
+    if levels.oceans >= MAX_OCEANS {
+        return chain;
+    }
+    levels.oceans += 1;
+    chain.join(QueueTile{player_id: player_id, tile: tile})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::{Milestones, Awards, SOLO_GENERATIONS};
+    use crate::card::Deck;
+    use crate::event::Trigger;
+    use crate::colony::{ColonyTile, TradePayment, TRADE_MEGACREDITS, TRADE_ENERGY, TRADE_TITANIUM};
+    use crate::test_utils::{action_game, basic_game, find_card, give_card, load_cards, put_in_play};
+
+    fn play(owner_id: usize, card_id: &str) -> CmdWrapper {
+        CmdWrapper::PlayCard(PlayCard{
+            owner_id,
+            card_id: card_id.to_owned(),
+            target_id: None,
+            target_card: None,
+            choice: None,
+            payment: None,
+            colony: None,
+            paid: None,
+        })
+    }
+
+    fn place(player_id: usize, x: u32, z: u32) -> CmdWrapper {
+        CmdWrapper::PlaceTile(PlaceTile{player_id, x, z, placed: None, turn: None})
+    }
+
+    #[test]
+    fn playing_a_card_executes_its_effects() {
+        let (mut state, cards) = basic_game(2);
+        let card_id = give_card(&mut state, &cards, 0, "Power Plant");
+        state.players[0].inventory.megacredits = 10;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(play(0, &card_id)).unwrap();
+        let player = &state_machine.get_state().players[0];
+        assert_eq!(player.inventory.megacredits, 6);
+        assert_eq!(player.production.energy, 2);
+        assert!(player.hand.is_empty());
+        assert!(state_machine.get_state().cards_in_play.iter().any(|c| c.card.id == card_id));
+    }
+
+    #[test]
+    fn a_card_with_an_unaffordable_effect_is_not_played() {
+        let (mut state, cards) = basic_game(2);
+        let card_id = give_card(&mut state, &cards, 0, "Local Heat Trapping");
+        state.players[0].inventory.megacredits = 1;
+        state.players[0].inventory.heat = 4;
+        let mut state_machine = StateMachine::new(state, cards);
+        assert!(state_machine.apply(play(0, &card_id)).is_err());
+        let player = &state_machine.get_state().players[0];
+        assert_eq!(player.inventory.megacredits, 1);
+        assert_eq!(player.inventory.heat, 4);
+        assert_eq!(player.hand.len(), 1);
+        assert_eq!(player.action_state, ActionState::Acting(ACTIONS_PER_TURN));
+    }
+
+    #[test]
+    fn a_card_spends_and_gains_resources() {
+        let (mut state, cards) = basic_game(2);
+        let card_id = give_card(&mut state, &cards, 0, "Local Heat Trapping");
+        state.players[0].inventory.megacredits = 1;
+        state.players[0].inventory.heat = 5;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(play(0, &card_id)).unwrap();
+        let player = &state_machine.get_state().players[0];
+        assert_eq!(player.inventory.heat, 0);
+        assert_eq!(player.inventory.plants, 4);
+    }
+
+    #[test]
+    fn tiles_are_placed_in_the_own_turn() {
+        let (mut state, cards) = basic_game(2);
+        state.players[1].pending_tiles.push(TilePlacement{tile_type: TileType::City, placement: Placement::Normal});
+        let mut state_machine = StateMachine::new(state, cards);
+        assert!(state_machine.apply(place(1, 2, 4)).is_err());
+        assert_eq!(state_machine.get_state().players[1].pending_tiles.len(), 1);
+    }
+
+    #[test]
+    fn pending_tiles_are_placed_before_anything_else() {
+        let (mut state, cards) = basic_game(2);
+        state.players[0].inventory.megacredits = 30;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(CmdWrapper::City(StandardProject{player_id: 0})).unwrap();
+        assert!(state_machine.apply(CmdWrapper::EndTurn(EndTurn{player_id: 0, turn: None})).is_err());
+        assert!(state_machine.apply(CmdWrapper::Pass(Pass{player_id: 0, turn: None})).is_err());
+        assert!(state_machine.apply(CmdWrapper::PowerPlant(StandardProject{player_id: 0})).is_err());
+        state_machine.apply(place(0, 2, 4)).unwrap();
+        let state = state_machine.get_state();
+        assert_eq!(state.tiles.iter().find(|t| t.x == 2 && t.z == 4).unwrap().owner, Some(0));
+        assert_eq!(state.players[0].inventory.megacredits, 5);
+    }
+
+    #[test]
+    fn the_turn_ends_once_the_tile_of_the_last_action_is_placed() {
+        let (mut state, cards) = basic_game(2);
+        state.players[0].inventory.megacredits = 36;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(CmdWrapper::PowerPlant(StandardProject{player_id: 0})).unwrap();
+        state_machine.apply(CmdWrapper::City(StandardProject{player_id: 0})).unwrap();
+        assert_eq!(state_machine.get_state().active_player, 0);
+        assert_eq!(state_machine.get_state().players[0].action_state, ActionState::Acting(0));
+        state_machine.apply(place(0, 2, 4)).unwrap();
+        let state = state_machine.get_state();
+        assert_eq!(state.active_player, 1);
+        assert_eq!(state.players[0].action_state, ActionState::Waiting);
+        assert_eq!(state.players[1].action_state, ActionState::Acting(ACTIONS_PER_TURN));
+    }
+
+    #[test]
+    fn temperature_steps_at_minus_24_and_minus_20_raise_the_heat_production() {
+        let (mut state, cards) = basic_game(2);
+        state.temperature = -26;
+        state.players[0].inventory.megacredits = 28;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(CmdWrapper::Asteroid(StandardProject{player_id: 0})).unwrap();
+        assert_eq!(state_machine.get_state().temperature, -24);
+        assert_eq!(state_machine.get_state().players[0].production.heat, 2);
+        assert_eq!(state_machine.get_state().players[0].tf_rating, 21);
+        state_machine.apply(CmdWrapper::Asteroid(StandardProject{player_id: 0})).unwrap();
+        assert_eq!(state_machine.get_state().temperature, -22);
+        assert_eq!(state_machine.get_state().players[0].production.heat, 2);
+    }
+
+    #[test]
+    fn the_temperature_step_to_zero_places_an_ocean() {
+        let (mut state, cards) = basic_game(2);
+        state.temperature = -2;
+        state.players[0].inventory.megacredits = 14;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(CmdWrapper::Asteroid(StandardProject{player_id: 0})).unwrap();
+        let player = &state_machine.get_state().players[0];
+        assert_eq!(player.pending_tiles, vec![TilePlacement{tile_type: TileType::Ocean, placement: Placement::Normal}]);
+        state_machine.apply(place(0, 0, 5)).unwrap();
+        assert_eq!(state_machine.get_state().oceans_placed, 1);
+        assert_eq!(state_machine.get_state().players[0].tf_rating, 22);
+    }
+
+    #[test]
+    fn the_oxygen_step_to_8_percent_raises_the_temperature() {
+        let (mut state, cards) = basic_game(2);
+        state.oxygen = 7;
+        state.players[0].inventory.megacredits = 23;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(CmdWrapper::Greenery(StandardProject{player_id: 0})).unwrap();
+        state_machine.apply(place(0, 6, 0)).unwrap();
+        let state = state_machine.get_state();
+        assert_eq!(state.oxygen, 8);
+        assert_eq!(state.temperature, -28);
+        assert_eq!(state.players[0].tf_rating, 22);
+    }
+
+    #[test]
+    fn steps_beyond_the_maximum_do_not_raise_the_rating() {
+        let (mut state, cards) = basic_game(2);
+        state.temperature = MAX_TEMPERATURE;
+        state.players[0].inventory.megacredits = 14;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(CmdWrapper::Asteroid(StandardProject{player_id: 0})).unwrap();
+        assert_eq!(state_machine.get_state().temperature, MAX_TEMPERATURE);
+        assert_eq!(state_machine.get_state().players[0].tf_rating, 20);
+    }
+
+    #[test]
+    fn no_more_than_9_oceans_are_handed_out() {
+        let (mut state, cards) = basic_game(2);
+        state.oceans_placed = MAX_OCEANS - 1;
+        state.players[1].pending_tiles.push(TilePlacement{tile_type: TileType::Ocean, placement: Placement::Normal});
+        state.players[0].inventory.megacredits = 18;
+        let mut state_machine = StateMachine::new(state, cards);
+        assert!(state_machine.apply(CmdWrapper::Aquifer(StandardProject{player_id: 0})).is_err());
+        assert_eq!(state_machine.get_state().players[0].inventory.megacredits, 18);
+    }
+
+    #[test]
+    fn standard_projects_are_paid_with_megacredits() {
+        let (mut state, cards) = basic_game(2);
+        state.players[0].inventory.megacredits = POWER_PLANT_COST + 1;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(CmdWrapper::PowerPlant(StandardProject{player_id: 0})).unwrap();
+        let player = &state_machine.get_state().players[0];
+        assert_eq!(player.inventory.megacredits, 1);
+        assert_eq!(player.production.energy, 2);
+        assert_eq!(player.action_state, ActionState::Acting(1));
+    }
+
+    #[test]
+    fn unaffordable_standard_projects_are_rejected() {
+        let (mut state, cards) = basic_game(2);
+        state.players[0].inventory.megacredits = CITY_COST - 1;
+        let mut state_machine = StateMachine::new(state, cards);
+        assert!(state_machine.apply(CmdWrapper::City(StandardProject{player_id: 0})).is_err());
+        let player = &state_machine.get_state().players[0];
+        assert_eq!(player.inventory.megacredits, CITY_COST - 1);
+        assert_eq!(player.production.megacredits, 1);
+        assert!(player.pending_tiles.is_empty());
+        assert_eq!(player.action_state, ActionState::Acting(ACTIONS_PER_TURN));
+    }
+
+    #[test]
+    fn standard_projects_are_funded_in_the_own_turn() {
+        let (mut state, cards) = basic_game(2);
+        state.players[1].inventory.megacredits = 20;
+        let mut state_machine = StateMachine::new(state, cards);
+        assert!(state_machine.apply(CmdWrapper::PowerPlant(StandardProject{player_id: 1})).is_err());
+        assert_eq!(state_machine.get_state().players[1].inventory.megacredits, 20);
+    }
+
+    #[test]
+    fn plants_are_converted_into_a_greenery() {
+        let (mut state, cards) = basic_game(2);
+        state.players[0].inventory.plants = 7;
+        let mut state_machine = StateMachine::new(state, cards);
+        assert!(state_machine.apply(CmdWrapper::ConvertPlants(ConvertPlants{player_id: 0})).is_err());
+        state_machine.record.as_mut_target().players[0].inventory.plants = 9;
+        state_machine.apply(CmdWrapper::ConvertPlants(ConvertPlants{player_id: 0})).unwrap();
+        state_machine.apply(place(0, 6, 0)).unwrap();
+        let state = state_machine.get_state();
+        assert_eq!(state.players[0].inventory.plants, 1);
+        assert_eq!(state.oxygen, 1);
+        assert_eq!(state.players[0].tf_rating, 21);
+    }
+
+    #[test]
+    fn ecoline_needs_only_7_plants_for_a_greenery() {
+        let (mut state, cards) = basic_game(2);
+        state.players[0].corporation = Some(find_card(&cards, "Ecoline"));
+        state.players[0].inventory.plants = 7;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(CmdWrapper::ConvertPlants(ConvertPlants{player_id: 0})).unwrap();
+        assert_eq!(state_machine.get_state().players[0].inventory.plants, 0);
+    }
+
+    #[test]
+    fn heat_is_converted_into_a_temperature_step() {
+        let (mut state, cards) = basic_game(2);
+        state.players[0].inventory.heat = 8;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(CmdWrapper::ConvertHeat(ConvertHeat{player_id: 0})).unwrap();
+        assert_eq!(state_machine.get_state().temperature, -28);
+        assert_eq!(state_machine.get_state().players[0].inventory.heat, 0);
+        assert!(state_machine.apply(CmdWrapper::ConvertHeat(ConvertHeat{player_id: 0})).is_err());
+    }
+
+    #[test]
+    fn heat_is_not_converted_at_the_maximum_temperature() {
+        let (mut state, cards) = basic_game(2);
+        state.temperature = MAX_TEMPERATURE;
+        state.players[0].inventory.heat = 8;
+        let mut state_machine = StateMachine::new(state, cards);
+        assert!(state_machine.apply(CmdWrapper::ConvertHeat(ConvertHeat{player_id: 0})).is_err());
+        assert_eq!(state_machine.get_state().players[0].inventory.heat, 8);
+    }
+
+    #[test]
+    fn all_players_convert_plants_in_the_final_greenery_round() {
+        let (mut state, cards) = basic_game(2);
+        state.phase = Phase::FinalGreenery;
+        state.players[1].inventory.plants = 8;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(CmdWrapper::Pass(Pass{player_id: 0, turn: None})).unwrap();
+        state_machine.apply(CmdWrapper::ConvertPlants(ConvertPlants{player_id: 1})).unwrap();
+        assert!(state_machine.apply(CmdWrapper::Pass(Pass{player_id: 1, turn: None})).is_err());
+        state_machine.apply(place(1, 6, 0)).unwrap();
+        assert_eq!(state_machine.get_state().players[1].inventory.plants, 0);
+        assert_eq!(state_machine.get_state().phase, Phase::FinalGreenery);
+        // the game ends once the last player is done
+        state_machine.apply(CmdWrapper::Pass(Pass{player_id: 1, turn: None})).unwrap();
+        assert_eq!(state_machine.get_state().phase, Phase::End);
+        assert_eq!(state_machine.get_state().final_scores.len(), 2);
+    }
+
+    #[test]
+    fn players_done_with_the_final_greeneries_convert_no_more_plants() {
+        let (mut state, cards) = basic_game(2);
+        state.phase = Phase::FinalGreenery;
+        state.players[0].inventory.plants = 8;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(CmdWrapper::Pass(Pass{player_id: 0, turn: None})).unwrap();
+        assert!(state_machine.apply(CmdWrapper::Pass(Pass{player_id: 0, turn: None})).is_err());
+        assert!(state_machine.apply(CmdWrapper::ConvertPlants(ConvertPlants{player_id: 0})).is_err());
+        assert_eq!(state_machine.get_state().players[0].inventory.plants, 8);
+    }
+
+    #[test]
+    fn the_turn_passes_after_two_actions() {
+        let (mut state, cards) = basic_game(3);
+        state.players[0].inventory.megacredits = 2 * POWER_PLANT_COST;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(CmdWrapper::PowerPlant(StandardProject{player_id: 0})).unwrap();
+        assert_eq!(state_machine.get_state().active_player, 0);
+        state_machine.apply(CmdWrapper::PowerPlant(StandardProject{player_id: 0})).unwrap();
+        assert_eq!(state_machine.get_state().active_player, 1);
+        assert_eq!(state_machine.get_state().players[1].action_state, ActionState::Acting(ACTIONS_PER_TURN));
+    }
+
+    #[test]
+    fn the_turn_is_ended_after_one_action_but_not_before() {
+        let (mut state, cards) = basic_game(2);
+        state.players[0].inventory.megacredits = POWER_PLANT_COST;
+        let mut state_machine = StateMachine::new(state, cards);
+        assert!(state_machine.apply(CmdWrapper::EndTurn(EndTurn{player_id: 0, turn: None})).is_err());
+        state_machine.apply(CmdWrapper::PowerPlant(StandardProject{player_id: 0})).unwrap();
+        state_machine.apply(CmdWrapper::EndTurn(EndTurn{player_id: 0, turn: None})).unwrap();
+        assert_eq!(state_machine.get_state().active_player, 1);
+        assert_eq!(state_machine.get_state().players[0].action_state, ActionState::Waiting);
+    }
+
+    #[test]
+    fn players_who_passed_are_skipped() {
+        let (mut state, cards) = basic_game(3);
+        state.players[1].action_state = ActionState::Passed;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(CmdWrapper::Pass(Pass{player_id: 0, turn: None})).unwrap();
+        assert_eq!(state_machine.get_state().active_player, 2);
+        assert_eq!(state_machine.get_state().players[0].action_state, ActionState::Passed);
+    }
+
+    #[test]
+    fn the_production_phase_follows_once_all_players_passed() {
+        let (state, cards) = basic_game(2);
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(CmdWrapper::Pass(Pass{player_id: 0, turn: None})).unwrap();
+        assert_eq!(state_machine.get_state().phase, Phase::Action);
+        state_machine.apply(CmdWrapper::Pass(Pass{player_id: 1, turn: None})).unwrap();
+        let state = state_machine.get_state();
+        assert_eq!(state.phase, Phase::Research);
+        assert_eq!(state.generation, 1);
+        assert_eq!(state.start_player, 1);
+        // production plus the terraform rating, energy turns into heat
+        assert_eq!(state.players[0].inventory.megacredits, 21);
+        assert_eq!(state.players[0].inventory.energy, 1);
+        assert_eq!(state.players[0].inventory.heat, 1);
+        assert_eq!(state.players[0].research_queue.len(), 4);
+    }
+
+    #[test]
+    fn the_corporations_first_action_comes_first() {
+        let (mut state, cards) = basic_game(2);
+        state.players[0].corporation = Some(find_card(&cards, "Tharsis Republic"));
+        state.players[0].pending_first_action = true;
+        state.players[0].inventory.megacredits = 40;
+        let mut state_machine = StateMachine::new(state, cards);
+        assert!(state_machine.apply(CmdWrapper::PowerPlant(StandardProject{player_id: 0})).is_err());
+        assert!(state_machine.apply(CmdWrapper::Pass(Pass{player_id: 0, turn: None})).is_err());
+        state_machine.apply(CmdWrapper::FirstAction(FirstAction{player_id: 0, colony: None})).unwrap();
+        assert!(!state_machine.get_state().players[0].pending_first_action);
+        assert_eq!(state_machine.get_state().players[0].pending_tiles.len(), 1);
+        state_machine.apply(place(0, 2, 4)).unwrap();
+        state_machine.apply(CmdWrapper::PowerPlant(StandardProject{player_id: 0})).unwrap();
+        assert_eq!(state_machine.get_state().active_player, 1);
+    }
+
+    #[test]
+    fn the_game_ends_with_the_final_scores() {
+        let (mut state, cards) = basic_game(2);
+        state.phase = Phase::FinalGreenery;
+        state.players[1].tf_rating = 25;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.advance_phase().unwrap();
+        let state = state_machine.get_state();
+        assert_eq!(state.phase, Phase::End);
+        assert_eq!(state.final_scores.iter().map(|s| s.total).collect::<Vec<i32>>(), vec![20, 25]);
+        assert!(state_machine.advance_phase().is_err());
+    }
+
+    #[test]
+    fn the_game_ends_after_the_generation_the_planet_is_terraformed() {
+        let (mut state, cards) = basic_game(2);
+        state.temperature = MAX_TEMPERATURE;
+        state.oxygen = MAX_OXYGEN;
+        state.oceans_placed = MAX_OCEANS;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(CmdWrapper::Pass(Pass{player_id: 0, turn: None})).unwrap();
+        state_machine.apply(CmdWrapper::Pass(Pass{player_id: 1, turn: None})).unwrap();
+        assert_eq!(state_machine.get_state().phase, Phase::FinalGreenery);
+        assert!(state_machine.get_state().players.iter().all(|p| p.action_state == ActionState::Waiting));
+    }
+
+    #[test]
+    fn milestones_are_claimed_once_their_requirement_is_met() {
+        let (mut state, cards) = basic_game(2);
+        state.players[0].inventory.megacredits = 2 * MILESTONE_COST;
+        state.players[0].tf_rating = 34;
+        let mut state_machine = StateMachine::new(state, cards);
+        let claim = || CmdWrapper::ClaimMilestone(ClaimMilestone{player_id: 0, milestone: Milestones::Terraformer});
+        assert!(state_machine.apply(claim()).is_err());
+        state_machine.record.as_mut_target().players[0].tf_rating = 35;
+        state_machine.apply(claim()).unwrap();
+        assert_eq!(state_machine.get_state().milestones[0].owner, Some(0));
+        assert_eq!(state_machine.get_state().players[0].inventory.megacredits, MILESTONE_COST);
+        assert!(state_machine.apply(claim()).is_err());
+    }
+
+    #[test]
+    fn each_funded_award_costs_more_and_there_are_three_at_most() {
+        let (mut state, cards) = basic_game(2);
+        state.players[0].inventory.megacredits = 100;
+        state.players[1].inventory.megacredits = 100;
+        let mut state_machine = StateMachine::new(state, cards);
+        let fund = |player_id, award| CmdWrapper::FundAward(FundAward{player_id, award});
+        state_machine.apply(fund(0, Awards::Landlord)).unwrap();
+        assert!(state_machine.apply(fund(0, Awards::Landlord)).is_err());
+        state_machine.apply(fund(0, Awards::Banker)).unwrap();
+        state_machine.apply(fund(1, Awards::Miner)).unwrap();
+        assert_eq!(state_machine.get_state().players[0].inventory.megacredits, 100 - AWARD_COSTS[0] - AWARD_COSTS[1]);
+        assert_eq!(state_machine.get_state().players[1].inventory.megacredits, 100 - AWARD_COSTS[2]);
+        assert!(state_machine.apply(fund(1, Awards::Thermalist)).is_err());
+        assert_eq!(state_machine.get_state().players[1].inventory.megacredits, 100 - AWARD_COSTS[2]);
+    }
+
+    fn use_action(player_id: usize, card_id: &str) -> CmdWrapper {
+        CmdWrapper::UseCardAction(UseCardAction{player_id, card_id: card_id.to_owned(), target_id: None, target_card: None, choice: None, payment: None})
+    }
+
+    #[test]
+    fn card_actions_are_used_once_per_generation() {
+        let (mut state, cards) = basic_game(2);
+        let birds = put_in_play(&mut state, &cards, 0, "Birds");
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(use_action(0, &birds)).unwrap();
+        assert!(state_machine.apply(use_action(0, &birds)).is_err());
+        let owned_card = state_machine.get_state().cards_in_play.iter().find(|c| c.card.id == birds).unwrap();
+        assert_eq!((owned_card.resources, owned_card.used), (1, true));
+        state_machine.apply(CmdWrapper::Pass(Pass{player_id: 0, turn: None})).unwrap();
+        state_machine.apply(CmdWrapper::Pass(Pass{player_id: 1, turn: None})).unwrap();
+        assert!(!state_machine.get_state().cards_in_play[0].used);
+    }
+
+    #[test]
+    fn card_actions_need_their_resources() {
+        let (mut state, cards) = basic_game(2);
+        let plant = put_in_play(&mut state, &cards, 0, "Water Splitting Plant");
+        state.players[0].inventory.energy = 2;
+        let mut state_machine = StateMachine::new(state, cards);
+        assert!(state_machine.apply(use_action(0, &plant)).is_err());
+        assert!(!state_machine.get_state().cards_in_play[0].used);
+        state_machine.record.as_mut_target().players[0].inventory.energy = 3;
+        state_machine.apply(use_action(0, &plant)).unwrap();
+        assert_eq!(state_machine.get_state().oxygen, 1);
+        assert_eq!(state_machine.get_state().players[0].inventory.energy, 0);
+    }
+
+    #[test]
+    fn only_own_cards_in_play_have_usable_actions() {
+        let (mut state, cards) = basic_game(2);
+        let birds = put_in_play(&mut state, &cards, 1, "Birds");
+        let power_plant = put_in_play(&mut state, &cards, 0, "Power Plant");
+        let mut state_machine = StateMachine::new(state, cards);
+        assert!(state_machine.apply(use_action(0, &birds)).is_err());
+        assert!(state_machine.apply(use_action(0, &power_plant)).is_err());
+    }
+
+    fn pluto_game() -> StateMachine {
+        let (mut state, cards) = action_game(2, &[Deck::Basic, Deck::Colonies]);
+        state.colonies = vec![ColonyTile::new(Colonies::Pluto)];
+        state.colonies[0].colonies.push(1);
+        state.players[0].inventory.megacredits = TRADE_MEGACREDITS;
+        let mut state_machine = StateMachine::new(state, cards);
+        let trade = Trade{player_id: 0, colony: Colonies::Pluto, payment: TradePayment::MegaCredits, target_card: None, track: None};
+        state_machine.apply(CmdWrapper::Trade(trade)).unwrap();
+        state_machine
+    }
+
+    fn discard(player_id: usize, card_ids: Vec<String>) -> CmdWrapper {
+        CmdWrapper::DiscardCards(DiscardCards{player_id, card_ids})
+    }
+
+    #[test]
+    fn pluto_colonists_discard_a_card_after_drawing() {
+        let mut state_machine = pluto_game();
+        let colonist = &state_machine.get_state().players[1];
+        assert_eq!((colonist.hand.len(), colonist.pending_discards), (1, 1));
+        let card_id = colonist.hand[0].id.to_owned();
+        // outside of their own turn
+        state_machine.apply(discard(1, vec![card_id])).unwrap();
+        let colonist = &state_machine.get_state().players[1];
+        assert_eq!((colonist.hand.len(), colonist.pending_discards), (0, 0));
+        assert_eq!(state_machine.get_state().project_pile.discard_pile.len(), 1);
+        assert!(state_machine.apply(discard(1, vec![])).is_err());
+    }
+
+    #[test]
+    fn pluto_colonists_discard_before_they_act() {
+        let mut state_machine = pluto_game();
+        state_machine.apply(CmdWrapper::EndTurn(EndTurn{player_id: 0, turn: None})).unwrap();
+        assert_eq!(state_machine.get_state().active_player, 1);
+        assert!(state_machine.apply(CmdWrapper::Pass(Pass{player_id: 1, turn: None})).is_err());
+        let card_id = state_machine.get_state().players[1].hand[0].id.to_owned();
+        state_machine.apply(discard(1, vec![card_id])).unwrap();
+        state_machine.apply(CmdWrapper::Pass(Pass{player_id: 1, turn: None})).unwrap();
+    }
+
+    #[test]
+    fn predators_eat_animals_of_another_card() {
+        let (mut state, cards) = basic_game(2);
+        let predators = put_in_play(&mut state, &cards, 0, "Predators");
+        let birds = put_in_play(&mut state, &cards, 1, "Birds");
+        let mut state_machine = StateMachine::new(state, cards);
+        let eat = |target_card: &str| CmdWrapper::UseCardAction(UseCardAction{
+            player_id: 0,
+            card_id: predators.to_owned(),
+            target_id: None,
+            target_card: Some(target_card.to_owned()),
+            choice: None,
+            payment: None,
+        });
+        assert!(state_machine.apply(eat(&birds)).is_err());
+        assert!(state_machine.apply(eat(&predators)).is_err());
+        state_machine.record.as_mut_target().cards_in_play[1].resources = 2;
+        state_machine.apply(eat(&birds)).unwrap();
+        let resources: Vec<u32> = state_machine.get_state().cards_in_play.iter().map(|c| c.resources).collect();
+        assert_eq!(resources, vec![1, 1]);
+    }
+
+    #[test]
+    fn triggers_react_to_the_tiles_of_all_players() {
+        let (mut state, cards) = basic_game(2);
+        put_in_play(&mut state, &cards, 1, "Rover Construction");
+        state.players[1].corporation = Some(find_card(&cards, "Tharsis Republic"));
+        state.players[0].pending_tiles.push(TilePlacement{tile_type: TileType::City, placement: Placement::Normal});
+        state.players[0].pending_tiles.push(TilePlacement{tile_type: TileType::City, placement: Placement::OffMars("Phobos Space Haven".to_owned())});
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(place(0, 2, 4)).unwrap();
+        let owner = &state_machine.get_state().players[1];
+        assert_eq!((owner.inventory.megacredits, owner.production.megacredits), (2, 2));
+        state_machine.apply(place(0, 0, 0)).unwrap();
+        let owner = &state_machine.get_state().players[1];
+        assert_eq!((owner.inventory.megacredits, owner.production.megacredits), (4, 2));
+    }
+
+    #[test]
+    fn splice_pays_the_player_of_a_microbe_tag() {
+        let (mut state, cards) = basic_game(2);
+        // Splice is not part of the card list yet, another corporation stands in for it
+        let mut splice = find_card(&cards, "Tharsis Republic");
+        splice.name = "Splice".to_owned();
+        splice.triggers = Trigger::card_triggers("Splice").unwrap();
+        state.players[1].corporation = Some(splice);
+        state.oxygen = 3;
+        let card_id = give_card(&mut state, &cards, 0, "Decomposers");
+        state.players[0].inventory.megacredits = 5;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(play(0, &card_id)).unwrap();
+        assert_eq!(state_machine.get_state().players[0].inventory.megacredits, 2);
+        assert_eq!(state_machine.get_state().players[1].inventory.megacredits, 2);
+    }
+
+    #[test]
+    fn cards_are_played_within_the_relaxed_requirements() {
+        let (mut state, cards) = basic_game(2);
+        state.temperature = -28;
+        let card_id = give_card(&mut state, &cards, 0, "Lichen");
+        state.players[0].inventory.megacredits = 7;
+        let mut state_machine = StateMachine::new(state, cards.to_owned());
+        assert!(state_machine.apply(play(0, &card_id)).is_err());
+        state_machine.record.as_mut_target().players[0].corporation = Some(find_card(&cards, "Inventrix"));
+        state_machine.apply(play(0, &card_id)).unwrap();
+        assert_eq!(state_machine.get_state().players[0].production.plants, 2);
+    }
+
+    #[test]
+    fn discounted_cards_cost_less() {
+        let (mut state, cards) = basic_game(2);
+        put_in_play(&mut state, &cards, 0, "Earth Office");
+        let card_id = give_card(&mut state, &cards, 0, "Pets");
+        state.players[0].inventory.megacredits = 10;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(play(0, &card_id)).unwrap();
+        assert_eq!(state_machine.get_state().players[0].inventory.megacredits, 3);
+    }
+
+    fn play_targeting(owner_id: usize, card_id: &str, target_id: Option<usize>, choice: Option<usize>) -> CmdWrapper {
+        match play(owner_id, card_id) {
+            CmdWrapper::PlayCard(cmd) => CmdWrapper::PlayCard(PlayCard{target_id, choice, ..cmd}),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn production_decreases_of_other_players_need_a_target_that_can_afford_them() {
+        let (mut state, cards) = basic_game(2);
+        let card_id = give_card(&mut state, &cards, 0, "Heat Trappers");
+        state.players[0].inventory.megacredits = 6;
+        let mut state_machine = StateMachine::new(state, cards);
+        assert!(state_machine.apply(play(0, &card_id)).is_err());
+        assert!(state_machine.apply(play_targeting(0, &card_id, Some(1), None)).is_err());
+        assert!(state_machine.apply(play_targeting(0, &card_id, Some(2), None)).is_err());
+        // rejected before the card is paid for
+        assert_eq!(state_machine.get_state().players[0].inventory.megacredits, 6);
+        assert_eq!(state_machine.get_state().players[0].hand.len(), 1);
+        state_machine.record.as_mut_target().players[1].production.heat = 2;
+        state_machine.apply(play_targeting(0, &card_id, Some(1), None)).unwrap();
+        assert_eq!(state_machine.get_state().players[1].production.heat, 0);
+        assert_eq!(state_machine.get_state().players[0].production.energy, 2);
+    }
+
+    #[test]
+    fn megacredit_production_can_be_decreased_down_to_minus_5() {
+        let (mut state, cards) = basic_game(2);
+        let card_id = give_card(&mut state, &cards, 0, "Hackers");
+        state.players[0].inventory.megacredits = 3;
+        state.players[1].production.megacredits = -4;
+        let mut state_machine = StateMachine::new(state, cards);
+        assert!(state_machine.apply(play_targeting(0, &card_id, Some(1), None)).is_err());
+        state_machine.record.as_mut_target().players[1].production.megacredits = -3;
+        state_machine.apply(play_targeting(0, &card_id, Some(1), None)).unwrap();
+        assert_eq!(state_machine.get_state().players[1].production.megacredits, -5);
+        assert_eq!(state_machine.get_state().players[0].production.megacredits, 3);
+        assert_eq!(state_machine.get_state().players[0].production.energy, 0);
+    }
+
+    #[test]
+    fn removing_resources_is_optional_and_takes_what_the_target_has() {
+        let (mut state, cards) = basic_game(2);
+        let first = give_card(&mut state, &cards, 0, "Asteroid");
+        let second = give_card(&mut state, &cards, 0, "Big Asteroid");
+        state.players[0].inventory.megacredits = 41;
+        state.players[1].inventory.plants = 2;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(play_targeting(0, &first, Some(1), None)).unwrap();
+        assert_eq!(state_machine.get_state().players[1].inventory.plants, 0);
+        assert_eq!(state_machine.get_state().players[0].inventory.titanium, 2);
+        state_machine.record.as_mut_target().players[1].inventory.plants = 5;
+        state_machine.apply(play(0, &second)).unwrap();
+        assert_eq!(state_machine.get_state().players[1].inventory.plants, 5);
+        assert_eq!(state_machine.get_state().players[0].inventory.titanium, 6);
+    }
+
+    #[test]
+    fn stealing_needs_an_opponent_as_target() {
+        let (mut state, cards) = basic_game(2);
+        let card_id = give_card(&mut state, &cards, 0, "Hired Raiders");
+        let cost = find_card(&cards, "Hired Raiders").cost;
+        state.players[0].inventory.megacredits = cost;
+        state.players[1].inventory.steel = 1;
+        let mut state_machine = StateMachine::new(state, cards);
+        assert!(state_machine.apply(play_targeting(0, &card_id, Some(0), Some(0))).is_err());
+        state_machine.apply(play_targeting(0, &card_id, Some(1), Some(0))).unwrap();
+        assert_eq!(state_machine.get_state().players[1].inventory.steel, 0);
+        assert_eq!(state_machine.get_state().players[0].inventory.steel, 1);
+    }
+
+    fn prelude_game() -> (GameState, Vec<Card>) {
+        let (mut state, cards) = basic_game(2);
+        state.phase = Phase::Setup;
+        for player in state.players.iter_mut() {
+            player.corporation = Some(find_card(&cards, "Inventrix"));
+        }
+        state.players[0].preludes.push(find_card(&cards, "Allied Bank"));
+        state.players[1].preludes.push(find_card(&cards, "Aquifer Turbines"));
+        (state, cards)
+    }
+
+    #[test]
+    fn the_preludes_are_played_before_the_first_generation() {
+        let (mut state, cards) = prelude_game();
+        state.players[1].inventory.megacredits = 3;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.advance_phase().unwrap();
+        let state = state_machine.get_state();
+        assert_eq!(state.phase, Phase::Action);
+        assert!(state.players.iter().all(|p| p.preludes.is_empty()));
+        assert_eq!((state.players[0].production.megacredits, state.players[0].inventory.megacredits), (5, 3));
+        assert_eq!((state.players[1].production.energy, state.players[1].inventory.megacredits), (3, 0));
+        assert_eq!(state.players[1].pending_tiles.len(), 1);
+    }
+
+    #[test]
+    fn no_prelude_is_played_if_one_cannot_be_played() {
+        let (state, cards) = prelude_game();
+        let mut state_machine = StateMachine::new(state, cards);
+        assert!(state_machine.advance_phase().is_err());
+        let state = state_machine.get_state();
+        assert_eq!(state.phase, Phase::Setup);
+        assert_eq!(state.players[0].production.megacredits, 1);
+        assert_eq!(state.players[0].preludes.len(), 1);
+        assert_eq!(state.players[1].preludes.len(), 1);
+    }
+
+    #[test]
+    fn the_action_phase_waits_for_the_setup_choices() {
+        let (mut state, cards) = prelude_game();
+        state.players[1].inventory.megacredits = 3;
+        state.players[1].hand.push(find_card(&cards, "Biolabs"));
+        let mut state_machine = StateMachine::new(state, cards);
+        assert!(state_machine.advance_phase().is_err());
+        state_machine.record.as_mut_target().players[1].hand.clear();
+        state_machine.record.as_mut_target().players[1].corporation = None;
+        assert!(state_machine.advance_phase().is_err());
+        assert_eq!(state_machine.get_state().phase, Phase::Setup);
+    }
+
+    fn venus_game() -> (GameState, Vec<Card>) {
+        let (mut state, cards) = action_game(2, &[Deck::Basic, Deck::Venus]);
+        state.players[0].inventory.megacredits = 30;
+        (state, cards)
+    }
+
+    fn air_scrapping(player_id: usize) -> CmdWrapper {
+        CmdWrapper::AirScrapping(StandardProject{player_id})
+    }
+
+    #[test]
+    fn air_scrapping_raises_venus() {
+        let (mut state, cards) = basic_game(2);
+        state.players[0].inventory.megacredits = 30;
+        let mut state_machine = StateMachine::new(state, cards);
+        assert!(state_machine.apply(air_scrapping(0)).is_err());
+        let (state, cards) = venus_game();
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(air_scrapping(0)).unwrap();
+        let state = state_machine.get_state();
+        assert_eq!(state.venus, VENUS_STEP);
+        assert_eq!(state.players[0].inventory.megacredits, 30 - AIR_SCRAPPING_COST);
+        assert_eq!(state.players[0].tf_rating, 21);
+    }
+
+    #[test]
+    fn venus_draws_a_card_at_8_and_raises_the_rating_at_16_percent() {
+        let (mut state, cards) = venus_game();
+        state.venus = 6;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(air_scrapping(0)).unwrap();
+        assert_eq!(state_machine.get_state().players[0].hand.len(), 1);
+        assert_eq!(state_machine.get_state().players[0].tf_rating, 21);
+        state_machine.record.as_mut_target().venus = 14;
+        state_machine.apply(air_scrapping(0)).unwrap();
+        assert_eq!(state_machine.get_state().venus, 16);
+        assert_eq!(state_machine.get_state().players[0].hand.len(), 1);
+        assert_eq!(state_machine.get_state().players[0].tf_rating, 23);
+    }
+
+    #[test]
+    fn venus_cards_need_the_venus_requirement() {
+        let (mut state, cards) = venus_game();
+        let card_id = give_card(&mut state, &cards, 0, "Ishtar Mining");
+        state.venus = 6;
+        let mut state_machine = StateMachine::new(state, cards);
+        assert!(state_machine.apply(play(0, &card_id)).is_err());
+        state_machine.record.as_mut_target().venus = 8;
+        state_machine.apply(play(0, &card_id)).unwrap();
+        assert_eq!(state_machine.get_state().players[0].production.titanium, 2);
+    }
+
+    #[test]
+    fn venus_has_to_be_terraformed_with_venus_next() {
+        let (mut state, _) = venus_game();
+        state.temperature = MAX_TEMPERATURE;
+        state.oxygen = MAX_OXYGEN;
+        state.oceans_placed = MAX_OCEANS;
+        assert!(!state.is_terraformed());
+        state.venus = MAX_VENUS;
+        assert!(state.is_terraformed());
+        assert!(state.milestones.iter().any(|m| m.name == Milestones::Hoverlord));
+        assert!(state.awards.iter().any(|a| a.name == Awards::Venuphile));
+    }
+
+    fn colony_game(tiles: &[Colonies]) -> (GameState, Vec<Card>) {
+        let (mut state, cards) = action_game(2, &[Deck::Basic, Deck::Colonies]);
+        state.colonies = tiles.iter().map(|c| ColonyTile::new(*c)).collect();
+        (state, cards)
+    }
+
+    fn build_colony(player_id: usize, colony: Colonies) -> CmdWrapper {
+        CmdWrapper::BuildColony(BuildColony{player_id, colony, target_card: None})
+    }
+
+    fn trade(player_id: usize, colony: Colonies, payment: TradePayment) -> CmdWrapper {
+        CmdWrapper::Trade(Trade{player_id, colony, payment, target_card: None, track: None})
+    }
+
+    #[test]
+    fn building_a_colony_hands_out_the_placement_bonus() {
+        let (mut state, cards) = colony_game(&[Colonies::Luna, Colonies::Triton]);
+        state.players[0].inventory.megacredits = 2 * BUILD_COLONY_COST;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(build_colony(0, Colonies::Luna)).unwrap();
+        state_machine.apply(build_colony(0, Colonies::Triton)).unwrap();
+        let state = state_machine.get_state();
+        assert_eq!(state.players[0].inventory.megacredits, 0);
+        assert_eq!(state.players[0].production.megacredits, 3);
+        assert_eq!(state.players[0].inventory.titanium, 3);
+        assert_eq!(state.colonies[0].colonies, vec![0]);
+    }
+
+    #[test]
+    fn a_player_has_one_colony_per_tile() {
+        let (mut state, cards) = colony_game(&[Colonies::Luna]);
+        state.players[0].inventory.megacredits = 2 * BUILD_COLONY_COST;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(build_colony(0, Colonies::Luna)).unwrap();
+        assert!(state_machine.apply(build_colony(0, Colonies::Luna)).is_err());
+        assert!(state_machine.apply(build_colony(0, Colonies::Pluto)).is_err());
+        assert_eq!(state_machine.get_state().players[0].inventory.megacredits, BUILD_COLONY_COST);
+    }
+
+    #[test]
+    fn colonies_need_the_expansion() {
+        let (mut state, cards) = basic_game(2);
+        state.colonies = vec![ColonyTile::new(Colonies::Luna)];
+        state.players[0].inventory.megacredits = BUILD_COLONY_COST;
+        let mut state_machine = StateMachine::new(state, cards);
+        assert!(state_machine.apply(build_colony(0, Colonies::Luna)).is_err());
+    }
+
+    #[test]
+    fn colonies_push_the_track_marker() {
+        let (mut state, cards) = colony_game(&[Colonies::Luna]);
+        state.players[0].inventory.megacredits = BUILD_COLONY_COST;
+        state.players[1].inventory.megacredits = BUILD_COLONY_COST;
+        state.colonies[0].colonies.push(1);
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(build_colony(0, Colonies::Luna)).unwrap();
+        assert_eq!(state_machine.get_state().colonies[0].track, 2);
+    }
+
+    #[test]
+    fn traders_collect_the_income_and_colonists_the_colony_bonus() {
+        let (mut state, cards) = colony_game(&[Colonies::Luna, Colonies::Ganymede]);
+        state.colonies[0].track = 3;
+        state.colonies[0].colonies = vec![1, 1];
+        state.players[0].inventory.megacredits = TRADE_MEGACREDITS;
+        state.players[0].inventory.energy = 3;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(trade(0, Colonies::Luna, TradePayment::MegaCredits)).unwrap();
+        let state = state_machine.get_state();
+        assert_eq!(state.players[0].inventory.megacredits, 7);
+        assert_eq!(state.players[1].inventory.megacredits, 4);
+        assert_eq!((state.colonies[0].track, state.colonies[0].trader), (2, Some(0)));
+        // a single trade fleet
+        assert!(state_machine.apply(trade(0, Colonies::Ganymede, TradePayment::Energy)).is_err());
+    }
+
+    #[test]
+    fn trading_needs_a_free_and_active_tile_and_the_payment() {
+        let (mut state, cards) = colony_game(&[Colonies::Luna, Colonies::Titan]);
+        state.players[0].inventory.energy = TRADE_ENERGY - 1;
+        state.players[1].inventory.titanium = TRADE_TITANIUM;
+        let mut state_machine = StateMachine::new(state, cards);
+        assert!(state_machine.apply(trade(0, Colonies::Luna, TradePayment::Energy)).is_err());
+        assert!(state_machine.apply(trade(0, Colonies::Titan, TradePayment::MegaCredits)).is_err());
+        assert!(state_machine.apply(trade(0, Colonies::Pluto, TradePayment::MegaCredits)).is_err());
+        assert_eq!(state_machine.get_state().colonies[0].trader, None);
+        state_machine.record.as_mut_target().players[0].inventory.energy = TRADE_ENERGY;
+        state_machine.apply(trade(0, Colonies::Luna, TradePayment::Energy)).unwrap();
+        state_machine.apply(CmdWrapper::Pass(Pass{player_id: 0, turn: None})).unwrap();
+        assert!(state_machine.apply(trade(1, Colonies::Luna, TradePayment::Titanium)).is_err());
+    }
+
+    #[test]
+    fn fleets_return_and_active_tracks_move_up_in_the_production_phase() {
+        let (mut state, cards) = colony_game(&[Colonies::Luna, Colonies::Titan]);
+        state.colonies[0].trader = Some(0);
+        state.colonies[0].track = MAX_TRACK;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.advance_phase().unwrap();
+        let colonies = &state_machine.get_state().colonies;
+        assert_eq!((colonies[0].track, colonies[0].trader), (MAX_TRACK, None));
+        assert_eq!(colonies[1].track, 1);
+    }
+
+    fn first_action(player_id: usize, colony: Option<Colonies>) -> CmdWrapper {
+        CmdWrapper::FirstAction(FirstAction{player_id, colony})
+    }
+
+    #[test]
+    fn the_first_action_is_taken_once() {
+        let (mut state, cards) = basic_game(2);
+        state.players[0].corporation = Some(find_card(&cards, "Inventrix"));
+        state.players[0].pending_first_action = true;
+        let mut state_machine = StateMachine::new(state, cards);
+        assert!(state_machine.apply(first_action(1, None)).is_err());
+        state_machine.apply(first_action(0, None)).unwrap();
+        assert_eq!(state_machine.get_state().players[0].hand.len(), 3);
+        assert!(state_machine.apply(first_action(0, None)).is_err());
+    }
+
+    #[test]
+    fn poseidon_places_a_colony_first() {
+        let (mut state, cards) = colony_game(&[Colonies::Ceres]);
+        state.players[0].corporation = Some(find_card(&cards, "Poseidon"));
+        state.players[0].pending_first_action = true;
+        let mut state_machine = StateMachine::new(state, cards);
+        assert!(state_machine.apply(first_action(0, Some(Colonies::Luna))).is_err());
+        state_machine.apply(first_action(0, Some(Colonies::Ceres))).unwrap();
+        assert_eq!(state_machine.get_state().colonies[0].colonies, vec![0]);
+        assert_eq!(state_machine.get_state().players[0].production.steel, 2);
+    }
+
+    fn draft(player_id: usize, card_id: &str) -> CmdWrapper {
+        CmdWrapper::DraftCard(DraftCard{player_id, card_id: card_id.to_owned(), index: None})
+    }
+
+    fn first_draft_card(state_machine: &StateMachine, player_id: usize) -> String {
+        state_machine.get_state().players[player_id].draft_hand[0].id.to_owned()
+    }
+
+    fn draft_ids(state_machine: &StateMachine, player_id: usize) -> Vec<String> {
+        state_machine.get_state().players[player_id].draft_hand.iter().map(|c| c.id.to_owned()).collect()
+    }
+
+    #[test]
+    fn the_research_phase_is_drafted() {
+        let (mut state, cards) = basic_game(3);
+        state.drafting = true;
+        state.phase = Phase::Production;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.advance_phase().unwrap();
+        assert!(state_machine.get_state().players.iter().all(|p| p.draft_hand.len() == 4 && p.research_queue.is_empty()));
+        let passed = draft_ids(&state_machine, 0)[1..].to_vec();
+        state_machine.apply(draft(0, &first_draft_card(&state_machine, 0))).unwrap();
+        assert!(state_machine.apply(draft(0, &first_draft_card(&state_machine, 0))).is_err());
+        for id in 1..3 {
+            state_machine.apply(draft(id, &first_draft_card(&state_machine, id))).unwrap();
+        }
+        // even generations pass to the next player
+        assert_eq!(draft_ids(&state_machine, 1), passed);
+        for _ in 0..2 {
+            for id in 0..3 {
+                state_machine.apply(draft(id, &first_draft_card(&state_machine, id))).unwrap();
+            }
+        }
+        let state = state_machine.get_state();
+        assert!(state.players.iter().all(|p| p.draft_hand.is_empty() && p.research_queue.len() == 4));
+    }
+
+    #[test]
+    fn cards_are_not_drafted_in_the_action_phase() {
+        let (mut state, cards) = basic_game(2);
+        state.players[0].draft_hand.push(find_card(&cards, "Pets"));
+        let card_id = state.players[0].draft_hand[0].id.to_owned();
+        let mut state_machine = StateMachine::new(state, cards);
+        assert!(state_machine.apply(draft(0, &card_id)).is_err());
+    }
+
+    // generations are counted from 0
+    fn solo_generation(generation: u32) -> StateMachine {
+        let (mut state, cards) = basic_game(1);
+        state.generation = generation;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(CmdWrapper::Pass(Pass{player_id: 0, turn: None})).unwrap();
+        state_machine
+    }
+
+    #[test]
+    fn a_solo_game_lasts_14_generations() {
+        let state_machine = solo_generation(SOLO_GENERATIONS - 2);
+        assert_eq!(state_machine.get_state().phase, Phase::Research);
+    }
+
+    #[test]
+    fn a_solo_game_is_lost_if_the_planet_is_not_terraformed_in_time() {
+        let mut state_machine = solo_generation(SOLO_GENERATIONS - 1);
+        assert_eq!(state_machine.get_state().phase, Phase::FinalGreenery);
+        state_machine.apply(CmdWrapper::Pass(Pass{player_id: 0, turn: None})).unwrap();
+        assert_eq!(state_machine.get_state().solo_won, Some(false));
+    }
+
+    #[test]
+    fn a_solo_game_is_won_by_terraforming_the_planet() {
+        let mut state_machine = solo_generation(SOLO_GENERATIONS - 1);
+        let state = state_machine.record.as_mut_target();
+        state.temperature = MAX_TEMPERATURE;
+        state.oxygen = MAX_OXYGEN;
+        state.oceans_placed = MAX_OCEANS;
+        state_machine.apply(CmdWrapper::Pass(Pass{player_id: 0, turn: None})).unwrap();
+        assert_eq!(state_machine.get_state().solo_won, Some(true));
+    }
+
+    #[test]
+    fn multiplayer_games_have_no_winner_flag() {
+        let (mut state, cards) = basic_game(2);
+        state.phase = Phase::FinalGreenery;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.advance_phase().unwrap();
+        assert_eq!(state_machine.get_state().solo_won, None);
+    }
+
+    #[test]
+    fn unknown_cards_are_rejected() {
+        let (state, cards) = basic_game(2);
+        let mut state_machine = StateMachine::new(state, cards);
+        assert!(state_machine.apply(play(0, "unknown")).is_err());
+        assert!(state_machine.apply(use_action(0, "unknown")).is_err());
+    }
+
+    fn place_pending_tiles(state_machine: &mut StateMachine, player_id: usize) {
+        while let Some(tile) = state_machine.get_state().players[player_id].pending_tiles.first().cloned() {
+            let state = state_machine.get_state();
+            let (x, z) = state.tiles.iter()
+                .map(|t| (t.x, t.z))
+                .find(|(x, z)| board::check_placement(state, player_id, &tile, *x, *z).is_ok())
+                .unwrap();
+            state_machine.apply(place(player_id, x, z)).unwrap();
+        }
+    }
+
+    #[test]
+    fn a_game_runs_from_the_setup_into_the_second_generation() {
+        let mut cards = load_cards();
+        let state = GameState::new(cards.as_mut(), &vec![Deck::Basic], 2, false);
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.advance_phase().unwrap();
+        assert_eq!(state_machine.get_state().phase, Phase::Setup);
+        for player_id in 0..2 {
+            let player = &state_machine.get_state().players[player_id];
+            let card_id = player.hand[0].id.to_owned();
+            let card_ids = player.research_queue[..2].iter().map(|c| c.id.to_owned()).collect();
+            state_machine.apply(CmdWrapper::ChooseCorporation(ChooseCorporation{player_id, card_id, rejected: 0})).unwrap();
+            state_machine.apply(CmdWrapper::ResearchCards(ResearchCards{player_id, card_ids})).unwrap();
+        }
+        // the last choice starts the action phase
+        assert_eq!(state_machine.get_state().phase, Phase::Action);
+        while state_machine.get_state().phase == Phase::Action {
+            let player_id = state_machine.get_state().active_player;
+            if state_machine.get_state().players[player_id].pending_first_action {
+                state_machine.apply(first_action(player_id, None)).unwrap();
+                place_pending_tiles(&mut state_machine, player_id);
+            }
+            state_machine.apply(CmdWrapper::Pass(Pass{player_id, turn: None})).unwrap();
+        }
+        let state = state_machine.get_state();
+        assert_eq!((&state.phase, state.generation), (&Phase::Research, 1));
+        assert!(state.players.iter().all(|p| p.hand.len() >= 2 && p.research_queue.len() == 4));
+    }
+
+    fn research(player_id: usize, card_ids: Vec<String>) -> CmdWrapper {
+        CmdWrapper::ResearchCards(ResearchCards{player_id, card_ids})
+    }
+
+    #[test]
+    fn the_action_phase_starts_after_the_last_research() {
+        let (mut state, cards) = basic_game(2);
+        state.phase = Phase::Production;
+        state.start_player = 1;
+        state.players[1].inventory.megacredits = 3;
+        for player in state.players.iter_mut() {
+            player.corporation = Some(find_card(&cards, "Inventrix"));
+        }
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.advance_phase().unwrap();
+        state_machine.apply(research(0, vec![])).unwrap();
+        assert_eq!(state_machine.get_state().phase, Phase::Research);
+        let card_id = state_machine.get_state().players[1].research_queue[0].id.to_owned();
+        state_machine.apply(research(1, vec![card_id])).unwrap();
+        let state = state_machine.get_state();
+        assert_eq!(state.phase, Phase::Action);
+        assert_eq!(state.players[1].action_state, ActionState::Acting(ACTIONS_PER_TURN));
+        assert_eq!(state.players[1].hand.len(), 1);
+    }
+
+    #[test]
+    fn the_last_setup_choice_is_taken_back_if_a_prelude_cannot_be_played() {
+        let (mut state, cards) = prelude_game();
+        state.players[0].preludes = vec![find_card(&cards, "Aquifer Turbines")];
+        state.players[1].preludes = vec![find_card(&cards, "Allied Bank")];
+        state.players[1].corporation = None;
+        let card_id = give_card(&mut state, &cards, 1, "Inventrix");
+        let mut state_machine = StateMachine::new(state, cards);
+        let choice = ChooseCorporation{player_id: 1, card_id, rejected: 0};
+        assert!(state_machine.apply(CmdWrapper::ChooseCorporation(choice)).is_err());
+        let state = state_machine.get_state();
+        assert_eq!(state.phase, Phase::Setup);
+        assert!(state.players[1].corporation.is_none());
+        assert_eq!(state.players[1].hand.len(), 1);
+    }
+
+
+    #[test]
+    fn credicor_rewards_standard_projects_of_20_mc_or_more() {
+        let (mut state, cards) = basic_game(2);
+        state.players[0].corporation = Some(find_card(&cards, "CrediCor"));
+        state.players[0].inventory.megacredits = CITY_COST + POWER_PLANT_COST;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(CmdWrapper::City(StandardProject{player_id: 0})).unwrap();
+        assert_eq!(state_machine.get_state().players[0].inventory.megacredits, POWER_PLANT_COST + 4);
+        state_machine.apply(place(0, 2, 4)).unwrap();
+        state_machine.apply(CmdWrapper::PowerPlant(StandardProject{player_id: 0})).unwrap();
+        assert_eq!(state_machine.get_state().players[0].inventory.megacredits, 4);
+    }
+
+    #[test]
+    fn standard_technology_refunds_standard_projects() {
+        let (mut state, cards) = basic_game(2);
+        put_in_play(&mut state, &cards, 0, "Standard Technology");
+        put_in_play(&mut state, &cards, 1, "Spin-Off Department");
+        state.players[1].inventory.megacredits = CITY_COST;
+        state.players[0].inventory.megacredits = POWER_PLANT_COST;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(CmdWrapper::PowerPlant(StandardProject{player_id: 0})).unwrap();
+        assert_eq!(state_machine.get_state().players[0].inventory.megacredits, 3);
+        // the spin-off department only draws for cards
+        state_machine.record.as_mut_target().active_player = 1;
+        state_machine.record.as_mut_target().players[1].action_state = ActionState::Acting(2);
+        state_machine.apply(CmdWrapper::City(StandardProject{player_id: 1})).unwrap();
+        assert!(state_machine.get_state().players[1].hand.is_empty());
+    }
+
+    #[test]
+    fn mining_guild_raises_the_steel_production_for_steel_and_titanium_bonuses() {
+        let (mut state, cards) = basic_game(2);
+        state.players[0].corporation = Some(find_card(&cards, "Mining Guild"));
+        state.players[0].inventory.megacredits = 2 * GREENERY_COST;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.apply(CmdWrapper::Greenery(StandardProject{player_id: 0})).unwrap();
+        // Pavonis Mons hands out a plant and a titanium
+        state_machine.apply(place(0, 3, 1)).unwrap();
+        let player = &state_machine.get_state().players[0];
+        assert_eq!((player.inventory.titanium, player.production.steel), (1, 2));
+        state_machine.apply(CmdWrapper::Greenery(StandardProject{player_id: 0})).unwrap();
+        state_machine.apply(place(0, 3, 2)).unwrap();
+        assert_eq!(state_machine.get_state().players[0].production.steel, 2);
+    }
+
+
+    fn pay_action(player_id: usize, card_id: &str, megacredits: u32, steel: u32, titanium: u32) -> CmdWrapper {
+        let payment = Some(Payment{megacredits, steel, titanium});
+        CmdWrapper::UseCardAction(UseCardAction{player_id, card_id: card_id.to_owned(), target_id: None, target_card: None, choice: None, payment})
+    }
+
+    #[test]
+    fn aquifer_pumping_is_paid_with_steel() {
+        let (mut state, cards) = basic_game(2);
+        let pumping = put_in_play(&mut state, &cards, 0, "Aquifer Pumping");
+        state.players[0].inventory.steel = 4;
+        state.players[0].inventory.titanium = 3;
+        let mut state_machine = StateMachine::new(state, cards);
+        assert!(state_machine.apply(pay_action(0, &pumping, 0, 0, 3)).is_err());
+        assert!(state_machine.apply(pay_action(0, &pumping, 0, 5, 0)).is_err());
+        state_machine.apply(pay_action(0, &pumping, 0, 4, 0)).unwrap();
+        let player = &state_machine.get_state().players[0];
+        assert_eq!((player.inventory.steel, player.pending_tiles.len()), (0, 1));
+    }
+
+    #[test]
+    fn water_import_from_europa_is_paid_with_titanium() {
+        let (mut state, cards) = basic_game(2);
+        let water_import = put_in_play(&mut state, &cards, 0, "Water Import From Europa");
+        state.players[0].inventory.megacredits = 12;
+        state.players[0].inventory.steel = 6;
+        state.players[0].inventory.titanium = 3;
+        let mut state_machine = StateMachine::new(state, cards);
+        assert!(state_machine.apply(pay_action(0, &water_import, 0, 6, 0)).is_err());
+        state_machine.apply(pay_action(0, &water_import, 3, 0, 3)).unwrap();
+        let player = &state_machine.get_state().players[0];
+        assert_eq!((player.inventory.megacredits, player.inventory.titanium), (9, 0));
+        state_machine.record.undo();
+        // without a payment the action is paid with megacredits
+        state_machine.apply(use_action(0, &water_import)).unwrap();
+        assert_eq!(state_machine.get_state().players[0].inventory.megacredits, 0);
+    }
+
+    #[test]
+    fn thermophiles_feed_only_venus_cards() {
+        let (mut state, cards) = basic_game(2);
+        let thermophiles = put_in_play(&mut state, &cards, 0, "Thermophiles");
+        let decomposers = put_in_play(&mut state, &cards, 0, "Decomposers");
+        let bacteria = put_in_play(&mut state, &cards, 0, "Sulphur-Eating Bacteria");
+        let mut state_machine = StateMachine::new(state, cards);
+        let feed = |target_card: &str| CmdWrapper::UseCardAction(UseCardAction{
+            player_id: 0,
+            card_id: thermophiles.to_owned(),
+            target_id: None,
+            target_card: Some(target_card.to_owned()),
+            choice: Some(0),
+            payment: None,
+        });
+        assert!(state_machine.apply(feed(&decomposers)).is_err());
+        state_machine.apply(feed(&bacteria)).unwrap();
+        assert_eq!(state_machine.get_state().cards_in_play[2].resources, 1);
+    }
+
+
+    #[test]
+    fn preludes_are_only_played_when_leaving_the_setup() {
+        let (mut state, cards) = prelude_game();
+        state.phase = Phase::Research;
+        let mut state_machine = StateMachine::new(state, cards);
+        state_machine.advance_phase().unwrap();
+        let state = state_machine.get_state();
+        assert_eq!(state.phase, Phase::Action);
+        assert_eq!(state.players[0].preludes.len(), 1);
+        assert_eq!(state.players[0].production.megacredits, 1);
+    }
 }
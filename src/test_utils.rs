@@ -0,0 +1,57 @@
+use std::path::Path;
+use std::fs::File;
+use std::io::prelude::*;
+
+use crate::card::{Card, Deck};
+use crate::game_state::{GameState, Phase, OwnedCard};
+use crate::player::{ActionState, ACTIONS_PER_TURN};
+
+// all cards of the cards/ directory, loaded like the server does
+pub fn load_cards() -> Vec<Card> {
+    let mut all_cards = Vec::new();
+    for entry in Path::new("cards/").read_dir().unwrap().flatten() {
+        let path = entry.path();
+        if path.extension().unwrap() == "json" {
+            let mut content = String::new();
+            File::open(&path).unwrap().read_to_string(&mut content).unwrap();
+            all_cards.push(serde_json::from_str(&content).unwrap());
+        }
+    }
+    all_cards
+}
+
+pub fn find_card(cards: &[Card], name: &str) -> Card {
+    match cards.iter().find(|c| c.name == name) {
+        Some(card) => card.to_owned(),
+        None => panic!("Cannot find card {}", name),
+    }
+}
+
+// The first generation's action phase, player 0 starts. Nobody holds any cards or resources yet.
+pub fn action_game(player_count: usize, decks: &[Deck]) -> (GameState, Vec<Card>) {
+    let mut cards = load_cards();
+    let mut state = GameState::new(cards.as_mut(), &decks.to_vec(), player_count, false);
+    state.phase = Phase::Action;
+    state.start_player = 0;
+    state.active_player = 0;
+    state.players[0].action_state = ActionState::Acting(ACTIONS_PER_TURN);
+    (state, cards)
+}
+
+pub fn basic_game(player_count: usize) -> (GameState, Vec<Card>) {
+    action_game(player_count, &[Deck::Basic])
+}
+
+pub fn give_card(state: &mut GameState, cards: &[Card], player_id: usize, name: &str) -> String {
+    let card = find_card(cards, name);
+    let card_id = card.id.to_owned();
+    state.players[player_id].hand.push(card);
+    card_id
+}
+
+pub fn put_in_play(state: &mut GameState, cards: &[Card], player_id: usize, name: &str) -> String {
+    let card = find_card(cards, name);
+    let card_id = card.id.to_owned();
+    state.cards_in_play.push(OwnedCard{card, owner: player_id, resources: 0, used: false});
+    card_id
+}
@@ -0,0 +1,194 @@
+use serde::Serialize;
+
+use crate::game_state::{GameState, Phase, Milestone, Award, OwnedCard};
+use crate::player::{Player, ActionState, Inventory, Production};
+use crate::board::{Tile, SpecialTile};
+use crate::colony::ColonyTile;
+use crate::card::{Card, GlobalRequirements};
+use crate::card_pile::CardPile;
+use crate::effect::TilePlacement;
+use crate::score::FinalScore;
+
+// The game state as one player may see it. The cards in the hands of the opponents
+// and the order of the piles are hidden.
+#[derive(Serialize)]
+pub struct PlayerView<'a> {
+    pub player_id: usize,
+    pub phase: &'a Phase,
+    pub generation: u32,
+    pub start_player: usize,
+    pub active_player: usize,
+    pub oxygen: u32,
+    pub temperature: i32,
+    pub oceans_placed: u32,
+    pub venus: u32,
+    pub tiles: &'a Vec<Tile>,
+    pub special_tiles: &'a Vec<SpecialTile>,
+    pub colonies: &'a Vec<ColonyTile>,
+    pub milestones: &'a Vec<Milestone>,
+    pub awards: &'a Vec<Award>,
+    pub cards_in_play: &'a Vec<OwnedCard>,
+    pub players: Vec<PlayerInfo<'a>>,
+    // what the cards in the own hand cost and require after discounts and relaxed requirements
+    pub hand_costs: Vec<HandCost<'a>>,
+    pub project_pile: PileView,
+    pub corporation_pile: PileView,
+    pub prelude_pile: PileView,
+    pub drafting: bool,
+    pub corporate_era: bool,
+    pub use_preludes: bool,
+    pub use_venus: bool,
+    pub use_colonies: bool,
+    pub solo: bool,
+    pub final_scores: &'a Vec<FinalScore>,
+    pub solo_won: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub enum PlayerInfo<'a> {
+    Own(&'a Player),
+    Opponent(OpponentView<'a>),
+}
+
+// everything but the cards an opponent holds, which are only counted
+#[derive(Serialize)]
+pub struct OpponentView<'a> {
+    pub id: usize,
+    pub tf_rating: i32,
+    pub action_state: &'a ActionState,
+    pub corporation: Option<&'a Card>,
+    pub inventory: &'a Inventory,
+    pub production: &'a Production,
+    pub hand: usize,
+    pub research_queue: usize,
+    pub preludes: usize,
+    pub draft_hand: usize,
+    pub pending_tiles: &'a Vec<TilePlacement>,
+    pub pending_first_action: bool,
+    pub pending_discards: u32,
+}
+
+#[derive(Serialize)]
+pub struct HandCost<'a> {
+    pub name: &'a str,
+    pub cost: u32,
+    pub requirements: GlobalRequirements,
+}
+
+// only the number of cards in the piles
+#[derive(Serialize)]
+pub struct PileView {
+    pub draw_pile: usize,
+    pub discard_pile: usize,
+}
+
+impl<'a> OpponentView<'a> {
+    fn new(player: &'a Player) -> OpponentView<'a> {
+        OpponentView {
+            id: player.id,
+            tf_rating: player.tf_rating,
+            action_state: &player.action_state,
+            corporation: player.corporation.as_ref(),
+            inventory: &player.inventory,
+            production: &player.production,
+            hand: player.hand.len(),
+            research_queue: player.research_queue.len(),
+            preludes: player.preludes.len(),
+            draft_hand: player.draft_hand.len(),
+            pending_tiles: &player.pending_tiles,
+            pending_first_action: player.pending_first_action,
+            pending_discards: player.pending_discards,
+        }
+    }
+}
+
+impl PileView {
+    fn new(pile: &CardPile) -> PileView {
+        PileView {
+            draw_pile: pile.draw_pile.len(),
+            discard_pile: pile.discard_pile.len(),
+        }
+    }
+}
+
+impl<'a> PlayerView<'a> {
+    pub fn new(game_state: &'a GameState, player_id: usize) -> PlayerView<'a> {
+        PlayerView {
+            player_id: player_id,
+            phase: &game_state.phase,
+            generation: game_state.generation,
+            start_player: game_state.start_player,
+            active_player: game_state.active_player,
+            oxygen: game_state.oxygen,
+            temperature: game_state.temperature,
+            oceans_placed: game_state.oceans_placed,
+            venus: game_state.venus,
+            tiles: &game_state.tiles,
+            special_tiles: &game_state.special_tiles,
+            colonies: &game_state.colonies,
+            milestones: &game_state.milestones,
+            awards: &game_state.awards,
+            cards_in_play: &game_state.cards_in_play,
+            players: game_state.players.iter().map(|p| match p.id == player_id {
+                true => PlayerInfo::Own(p),
+                false => PlayerInfo::Opponent(OpponentView::new(p)),
+            }).collect(),
+            hand_costs: game_state.players.get(player_id).map_or(Vec::new(), |p| p.hand.iter().map(|card| HandCost {
+                name: &card.name,
+                cost: game_state.card_cost(player_id, card),
+                requirements: game_state.requirement_window(player_id, card),
+            }).collect()),
+            project_pile: PileView::new(&game_state.project_pile),
+            corporation_pile: PileView::new(&game_state.corporation_pile),
+            prelude_pile: PileView::new(&game_state.prelude_pile),
+            drafting: game_state.drafting,
+            corporate_era: game_state.corporate_era,
+            use_preludes: game_state.use_preludes,
+            use_venus: game_state.use_venus,
+            use_colonies: game_state.use_colonies,
+            solo: game_state.solo,
+            final_scores: &game_state.final_scores,
+            solo_won: game_state.solo_won,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{basic_game, find_card, give_card, put_in_play};
+
+    #[test]
+    fn the_own_hand_comes_with_the_effective_costs_and_requirements() {
+        let (mut game_state, cards) = basic_game(2);
+        give_card(&mut game_state, &cards, 0, "Lichen");
+        give_card(&mut game_state, &cards, 1, "Pets");
+        game_state.players[0].corporation = Some(find_card(&cards, "Inventrix"));
+        put_in_play(&mut game_state, &cards, 0, "Earth Catapult");
+        let view = PlayerView::new(&game_state, 0);
+        assert_eq!(view.hand_costs.len(), 1);
+        assert_eq!((view.hand_costs[0].name, view.hand_costs[0].cost), ("Lichen", 5));
+        assert_eq!(view.hand_costs[0].requirements.min_temperature, -28);
+        assert_eq!(PlayerView::new(&game_state, 1).hand_costs[0].cost, 10);
+    }
+
+    #[test]
+    fn opponents_cards_are_only_counted() {
+        let (mut game_state, cards) = basic_game(2);
+        let pets = give_card(&mut game_state, &cards, 0, "Pets");
+        let lichen = give_card(&mut game_state, &cards, 1, "Lichen");
+        let view = serde_json::to_value(PlayerView::new(&game_state, 0)).unwrap();
+        assert_eq!(view.pointer("/players/0/Own/hand/0/id"), Some(&serde_json::json!(pets)));
+        assert_eq!(view.pointer("/players/1/Opponent/hand"), Some(&serde_json::json!(1)));
+        assert!(!view.to_string().contains(&format!("\"id\":\"{}\"", lichen)));
+    }
+
+    #[test]
+    fn piles_show_their_size_only() {
+        let (game_state, _) = basic_game(2);
+        let view = serde_json::to_value(PlayerView::new(&game_state, 1)).unwrap();
+        let draw_pile = game_state.project_pile.draw_pile.len();
+        assert_eq!(view["project_pile"], serde_json::json!({"draw_pile": draw_pile, "discard_pile": 0}));
+        assert!(view.pointer("/players/1/Own").is_some());
+    }
+}